@@ -0,0 +1,144 @@
+use pyo3::prelude::*;
+use pyo3::types::PyDateTime;
+use std::sync::{Arc, RwLock};
+use std::time::{SystemTime, UNIX_EPOCH};
+use log::{info, error};
+use crate::models::order::extract_timestamp;
+
+/// Source of the current time for everything time-dependent in the engine.
+pub trait Clock: Send + Sync {
+    /// Seconds since the Unix epoch
+    fn now(&self) -> f64;
+}
+
+/// The system clock.
+pub struct WallClock;
+
+impl Clock for WallClock {
+    fn now(&self) -> f64 {
+        SystemTime::now().duration_since(UNIX_EPOCH).unwrap().as_secs_f64()
+    }
+}
+
+/// A clock stopped at one instant, for deterministic tests.
+pub struct FixedClock {
+    time: f64,
+}
+
+impl Clock for FixedClock {
+    fn now(&self) -> f64 {
+        self.time
+    }
+}
+
+/// A clock that only moves when told to, for backtests. Time never goes
+/// backwards.
+pub struct SimulatedClock {
+    time: RwLock<f64>,
+}
+
+impl SimulatedClock {
+    pub fn new(start: f64) -> Self {
+        SimulatedClock { time: RwLock::new(start) }
+    }
+
+    pub fn set(&self, timestamp: f64) -> PyResult<()> {
+        check_finite(timestamp)?;
+        let mut time = self.time.write().unwrap();
+        if timestamp < *time {
+            let err_msg = format!("Cannot move simulated clock back from {} to {}", *time, timestamp);
+            error!("{}", err_msg);
+            return Err(PyErr::new::<pyo3::exceptions::PyValueError, _>(err_msg));
+        }
+        *time = timestamp;
+        Ok(())
+    }
+}
+
+impl Clock for SimulatedClock {
+    fn now(&self) -> f64 {
+        *self.time.read().unwrap()
+    }
+}
+
+fn check_finite(timestamp: f64) -> PyResult<f64> {
+    if !timestamp.is_finite() {
+        let err_msg = format!("Clock time must be finite, got {}", timestamp);
+        error!("{}", err_msg);
+        return Err(PyErr::new::<pyo3::exceptions::PyValueError, _>(err_msg));
+    }
+    Ok(timestamp)
+}
+
+/// Python handle to a clock. Clones share the underlying clock, so advancing
+/// a simulated clock moves time for every validator and executor holding it.
+#[pyclass(name = "Clock")]
+#[derive(Clone)]
+pub struct ClockHandle {
+    clock: Arc<dyn Clock>,
+    simulated: Option<Arc<SimulatedClock>>,
+}
+
+impl Default for ClockHandle {
+    fn default() -> Self {
+        ClockHandle::from_clock(Arc::new(WallClock))
+    }
+}
+
+impl ClockHandle {
+    pub fn from_clock(clock: Arc<dyn Clock>) -> Self {
+        ClockHandle { clock, simulated: None }
+    }
+
+    fn simulated_clock(&self) -> PyResult<&SimulatedClock> {
+        self.simulated.as_deref().ok_or_else(|| {
+            PyErr::new::<pyo3::exceptions::PyTypeError, _>("Only a simulated clock can be moved")
+        })
+    }
+}
+
+#[pymethods]
+impl ClockHandle {
+    #[staticmethod]
+    pub fn wall() -> Self {
+        ClockHandle::default()
+    }
+
+    #[staticmethod]
+    pub fn fixed(time: &PyAny) -> PyResult<Self> {
+        Ok(ClockHandle::from_clock(Arc::new(FixedClock { time: check_finite(extract_timestamp(time)?)? })))
+    }
+
+    #[staticmethod]
+    pub fn simulated(start: &PyAny) -> PyResult<Self> {
+        let clock = Arc::new(SimulatedClock::new(check_finite(extract_timestamp(start)?)?));
+        Ok(ClockHandle { clock: clock.clone(), simulated: Some(clock) })
+    }
+
+    pub fn now(&self) -> f64 {
+        self.clock.now()
+    }
+
+    pub fn now_datetime<'py>(&self, py: Python<'py>) -> PyResult<&'py PyDateTime> {
+        PyDateTime::from_timestamp(py, self.now(), None)
+    }
+
+    #[getter]
+    pub fn is_simulated(&self) -> bool {
+        self.simulated.is_some()
+    }
+
+    /// Moves a simulated clock to `time`, a `datetime` or epoch seconds.
+    pub fn set(&self, time: &PyAny) -> PyResult<()> {
+        let timestamp = extract_timestamp(time)?;
+        self.simulated_clock()?.set(timestamp)?;
+        info!("Simulated clock set to {}", timestamp);
+        Ok(())
+    }
+
+    pub fn advance(&self, seconds: f64) -> PyResult<f64> {
+        let clock = self.simulated_clock()?;
+        clock.set(clock.now() + seconds)?;
+        Ok(clock.now())
+    }
+}
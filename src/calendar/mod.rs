@@ -0,0 +1,2 @@
+pub mod trading_calendar;
+pub mod clock;
\ No newline at end of file
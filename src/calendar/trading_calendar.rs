@@ -0,0 +1,270 @@
+use pyo3::prelude::*;
+use serde::Deserialize;
+use chrono::{DateTime, Datelike, NaiveDate, NaiveTime, Weekday};
+use chrono_tz::Tz;
+use std::collections::{HashMap, HashSet};
+use std::fmt;
+use std::fs;
+use std::sync::{Arc, RwLock};
+use log::{info, error};
+
+#[pyclass]
+#[derive(Debug, Clone, Copy, Eq, PartialEq, Hash)]
+pub enum TradingSession {
+    PreMarket,
+    Regular,
+    PostMarket,
+    Closed,
+}
+
+impl fmt::Display for TradingSession {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            TradingSession::PreMarket => write!(f, "pre-market"),
+            TradingSession::Regular => write!(f, "regular"),
+            TradingSession::PostMarket => write!(f, "post-market"),
+            TradingSession::Closed => write!(f, "closed"),
+        }
+    }
+}
+
+fn default_weekend() -> Vec<String> {
+    vec!["Sat".to_string(), "Sun".to_string()]
+}
+
+/// One exchange as written in a calendar file. Times are local to the
+/// exchange's timezone and written as `HH:MM`, dates as `YYYY-MM-DD`.
+#[derive(Debug, Clone, Deserialize)]
+pub struct ExchangeConfig {
+    pub timezone: String,
+    pub regular_open: String,
+    pub regular_close: String,
+    #[serde(default)]
+    pub pre_market_open: Option<String>,
+    #[serde(default)]
+    pub post_market_close: Option<String>,
+    #[serde(default = "default_weekend")]
+    pub weekend: Vec<String>,
+    #[serde(default)]
+    pub holidays: Vec<String>,
+    /// Early regular-session close by date
+    #[serde(default)]
+    pub half_days: HashMap<String, String>,
+}
+
+#[derive(Debug, Deserialize)]
+struct CalendarConfig {
+    exchanges: HashMap<String, ExchangeConfig>,
+}
+
+fn config_error(msg: String) -> PyErr {
+    error!("{}", msg);
+    PyErr::new::<pyo3::exceptions::PyValueError, _>(msg)
+}
+
+pub(crate) fn parse_time(value: &str) -> PyResult<NaiveTime> {
+    NaiveTime::parse_from_str(value, "%H:%M:%S")
+        .or_else(|_| NaiveTime::parse_from_str(value, "%H:%M"))
+        .map_err(|e| config_error(format!("Invalid session time {}: {}", value, e)))
+}
+
+fn parse_date(value: &str) -> PyResult<NaiveDate> {
+    NaiveDate::parse_from_str(value, "%Y-%m-%d")
+        .map_err(|e| config_error(format!("Invalid calendar date {}: {}", value, e)))
+}
+
+/// Sessions, weekends, holidays and half days for one exchange. Every
+/// session opens and closes within one local day, so sessions that run past
+/// midnight, as on some futures exchanges, are rejected rather than modelled.
+#[pyclass]
+#[derive(Debug, Clone)]
+pub struct ExchangeCalendar {
+    #[pyo3(get)]
+    pub exchange: String,
+    timezone: Tz,
+    pre_market_open: Option<NaiveTime>,
+    regular_open: NaiveTime,
+    regular_close: NaiveTime,
+    post_market_close: Option<NaiveTime>,
+    weekend: Vec<Weekday>,
+    holidays: HashSet<NaiveDate>,
+    half_days: HashMap<NaiveDate, NaiveTime>,
+}
+
+impl ExchangeCalendar {
+    pub fn from_config(exchange: String, config: &ExchangeConfig) -> PyResult<Self> {
+        let timezone: Tz = config.timezone.parse()
+            .map_err(|e| config_error(format!("Invalid timezone {} for {}: {}", config.timezone, exchange, e)))?;
+        let weekend = config.weekend.iter()
+            .map(|day| day.parse::<Weekday>().map_err(|_| config_error(format!("Invalid weekend day {} for {}", day, exchange))))
+            .collect::<PyResult<Vec<_>>>()?;
+        let holidays = config.holidays.iter().map(|d| parse_date(d)).collect::<PyResult<HashSet<_>>>()?;
+        let half_days = config.half_days.iter()
+            .map(|(date, close)| Ok((parse_date(date)?, parse_time(close)?)))
+            .collect::<PyResult<HashMap<_, _>>>()?;
+
+        let calendar = ExchangeCalendar {
+            exchange,
+            timezone,
+            pre_market_open: config.pre_market_open.as_deref().map(parse_time).transpose()?,
+            regular_open: parse_time(&config.regular_open)?,
+            regular_close: parse_time(&config.regular_close)?,
+            post_market_close: config.post_market_close.as_deref().map(parse_time).transpose()?,
+            weekend,
+            holidays,
+            half_days,
+        };
+
+        let ordered = calendar.pre_market_open.is_none_or(|open| open <= calendar.regular_open)
+            && calendar.regular_open < calendar.regular_close
+            && calendar.post_market_close.is_none_or(|close| calendar.regular_close <= close);
+        if !ordered {
+            return Err(config_error(format!("Sessions for {} must be pre-market, regular and post-market in that order", calendar.exchange)));
+        }
+        for (date, close) in &calendar.half_days {
+            calendar.check_half_day(*date, *close)?;
+        }
+        Ok(calendar)
+    }
+
+    /// An early close has to leave some of the regular session open.
+    fn check_half_day(&self, date: NaiveDate, close: NaiveTime) -> PyResult<()> {
+        if close <= self.regular_open || close > self.regular_close {
+            return Err(config_error(format!("Early close {} on {} for {} must be after the {} open and no later than the {} close",
+                                            close, date, self.exchange, self.regular_open, self.regular_close)));
+        }
+        Ok(())
+    }
+
+    fn local_time(&self, timestamp: f64) -> DateTime<Tz> {
+        let utc = DateTime::from_timestamp(timestamp.floor() as i64, 0).unwrap_or_default();
+        utc.with_timezone(&self.timezone)
+    }
+}
+
+#[pymethods]
+impl ExchangeCalendar {
+    #[new]
+    #[pyo3(signature = (exchange, timezone, regular_open, regular_close, pre_market_open=None, post_market_close=None,
+                        weekend=default_weekend(), holidays=Vec::new(), half_days=HashMap::new()))]
+    #[allow(clippy::too_many_arguments)]
+    pub fn new(exchange: String, timezone: String, regular_open: String, regular_close: String, pre_market_open: Option<String>,
+               post_market_close: Option<String>, weekend: Vec<String>, holidays: Vec<String>,
+               half_days: HashMap<String, String>) -> PyResult<Self> {
+        let config = ExchangeConfig { timezone, regular_open, regular_close, pre_market_open, post_market_close, weekend, holidays, half_days };
+        ExchangeCalendar::from_config(exchange, &config)
+    }
+
+    #[getter]
+    pub fn timezone(&self) -> String {
+        self.timezone.name().to_string()
+    }
+
+    pub fn add_holiday(&mut self, date: &str) -> PyResult<()> {
+        self.holidays.insert(parse_date(date)?);
+        Ok(())
+    }
+
+    pub fn add_half_day(&mut self, date: &str, close: &str) -> PyResult<()> {
+        let (date, close) = (parse_date(date)?, parse_time(close)?);
+        self.check_half_day(date, close)?;
+        self.half_days.insert(date, close);
+        Ok(())
+    }
+
+    /// Whether the exchange opens at all on the local date of `timestamp`.
+    pub fn is_trading_day(&self, timestamp: f64) -> bool {
+        let date = self.local_time(timestamp).date_naive();
+        !self.weekend.contains(&date.weekday()) && !self.holidays.contains(&date)
+    }
+
+    pub fn session_at(&self, timestamp: f64) -> TradingSession {
+        if !self.is_trading_day(timestamp) {
+            return TradingSession::Closed;
+        }
+        let local = self.local_time(timestamp);
+        let time = local.time();
+        let regular_close = self.half_days.get(&local.date_naive()).copied().unwrap_or(self.regular_close);
+
+        if time >= self.regular_open && time < regular_close {
+            TradingSession::Regular
+        } else if self.pre_market_open.is_some_and(|open| time >= open && time < self.regular_open) {
+            TradingSession::PreMarket
+        } else if self.post_market_close.is_some_and(|close| time >= regular_close && time < close) {
+            TradingSession::PostMarket
+        } else {
+            TradingSession::Closed
+        }
+    }
+}
+
+/// Calendars for every exchange, keyed by the `exchange` on `Instrument`.
+/// Clones share the same calendars, like `InstrumentRegistry`.
+#[pyclass]
+#[derive(Clone, Default)]
+pub struct TradingCalendar {
+    calendars: Arc<RwLock<HashMap<String, ExchangeCalendar>>>,
+}
+
+#[pymethods]
+impl TradingCalendar {
+    #[new]
+    pub fn new() -> Self {
+        TradingCalendar {
+            calendars: Arc::new(RwLock::new(HashMap::new())),
+        }
+    }
+
+    /// Loads a calendar file with an `[exchanges.<NAME>]` table per exchange.
+    /// Files ending in `.json` are read as JSON, anything else as TOML.
+    #[staticmethod]
+    pub fn from_file(path: &str) -> PyResult<Self> {
+        let contents = fs::read_to_string(path)
+            .map_err(|e| PyErr::new::<pyo3::exceptions::PyIOError, _>(format!("Cannot read calendar file {}: {}", path, e)))?;
+        let config: CalendarConfig = if path.ends_with(".json") {
+            serde_json::from_str(&contents).map_err(|e| config_error(format!("Invalid calendar file {}: {}", path, e)))?
+        } else {
+            toml::from_str(&contents).map_err(|e| config_error(format!("Invalid calendar file {}: {}", path, e)))?
+        };
+        TradingCalendar::from_config(config)
+    }
+
+    #[staticmethod]
+    pub fn from_toml(contents: &str) -> PyResult<Self> {
+        let config: CalendarConfig = toml::from_str(contents).map_err(|e| config_error(format!("Invalid calendar: {}", e)))?;
+        TradingCalendar::from_config(config)
+    }
+
+    pub fn add(&self, calendar: ExchangeCalendar) {
+        info!("Added trading calendar for {}", calendar.exchange);
+        self.calendars.write().unwrap().insert(calendar.exchange.clone(), calendar);
+    }
+
+    pub fn get(&self, exchange: &str) -> Option<ExchangeCalendar> {
+        self.calendars.read().unwrap().get(exchange).cloned()
+    }
+
+    pub fn exchanges(&self) -> Vec<String> {
+        self.calendars.read().unwrap().keys().cloned().collect()
+    }
+
+    pub fn session_at(&self, exchange: &str, timestamp: f64) -> PyResult<TradingSession> {
+        self.get(exchange)
+            .map(|calendar| calendar.session_at(timestamp))
+            .ok_or_else(|| PyErr::new::<pyo3::exceptions::PyKeyError, _>(format!("No trading calendar for {}", exchange)))
+    }
+
+    pub fn __len__(&self) -> usize {
+        self.calendars.read().unwrap().len()
+    }
+}
+
+impl TradingCalendar {
+    fn from_config(config: CalendarConfig) -> PyResult<Self> {
+        let calendar = TradingCalendar::new();
+        for (exchange, exchange_config) in config.exchanges {
+            calendar.add(ExchangeCalendar::from_config(exchange, &exchange_config)?);
+        }
+        Ok(calendar)
+    }
+}
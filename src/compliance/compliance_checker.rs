@@ -0,0 +1,150 @@
+use pyo3::prelude::*;
+use std::collections::HashMap;
+use log::{info, warn};
+use crate::models::order::Order;
+use crate::models::numeric::Decimal;
+use crate::validation::error::ValidationError;
+use crate::validation::report::ValidationReport;
+use crate::compliance::restricted_list::{RestrictedList, RestrictionLevel};
+use crate::compliance::locates::LocateInventory;
+
+/// Pre-trade compliance checks that run alongside `OrderValidator`: the
+/// restricted list and short-sale locates.
+#[pyclass]
+#[derive(Clone, Default)]
+pub struct ComplianceChecker {
+    restricted_list: RestrictedList,
+    locates: LocateInventory,
+    // Locates taken by orders that are still working, so they can be returned
+    reservations: HashMap<u64, (String, Decimal)>,
+}
+
+impl ComplianceChecker {
+    /// Part of the order that would open or extend a short position.
+    /// `current_position` should already be net of the account's other
+    /// working sales, so that two sales can't both count the same long.
+    pub fn short_quantity(order: &Order, current_position: Decimal) -> Decimal {
+        if order.side.is_buy() {
+            return Decimal::ZERO;
+        }
+        let new_position = current_position - order.quantity;
+        order.quantity.min(-new_position).max(Decimal::ZERO)
+    }
+
+    /// Checks that do not depend on the account's position.
+    pub fn check_restrictions(&self, order: &Order, errors: &mut Vec<ValidationError>, warnings: &mut Vec<ValidationError>) {
+        if let Some(restriction) = self.restricted_list.get(&order.symbol) {
+            let symbol = order.symbol.clone();
+            match restriction.level {
+                RestrictionLevel::Restricted => errors.push(ValidationError::RestrictedSymbol { symbol, reason: restriction.reason }),
+                RestrictionLevel::Watch => warnings.push(ValidationError::WatchListSymbol { symbol, reason: restriction.reason }),
+            }
+        }
+    }
+
+    /// Short sales need locates for the whole short quantity. An order that
+    /// already holds locates is only checked for what it adds.
+    pub fn check_short_sale(&self, order: &Order, current_position: Decimal,
+                            errors: &mut Vec<ValidationError>, warnings: &mut Vec<ValidationError>) {
+        let short_quantity = Self::short_quantity(order, current_position);
+        if short_quantity <= Decimal::ZERO {
+            return;
+        }
+        if self.locates.is_hard_to_borrow(&order.symbol) {
+            warnings.push(ValidationError::HardToBorrow { symbol: order.symbol.clone(), short_quantity });
+        }
+        let reserved = self.reserved(order.order_id);
+        let available = self.locates.available(&order.symbol);
+        if short_quantity > reserved + available {
+            errors.push(ValidationError::LocateRequired { symbol: order.symbol.clone(), short_quantity, available: reserved + available });
+        }
+    }
+
+    pub fn run_checks(&self, order: &Order, current_position: Decimal) -> (Vec<ValidationError>, Vec<ValidationError>) {
+        let mut errors = Vec::new();
+        let mut warnings = Vec::new();
+        self.check_restrictions(order, &mut errors, &mut warnings);
+        self.check_short_sale(order, current_position, &mut errors, &mut warnings);
+        (errors, warnings)
+    }
+
+    /// Raises the first failure and logs the warnings.
+    pub fn enforce(&self, order: &Order, current_position: Option<Decimal>) -> PyResult<()> {
+        let mut errors = Vec::new();
+        let mut warnings = Vec::new();
+        self.check_restrictions(order, &mut errors, &mut warnings);
+        if let Some(current_position) = current_position {
+            self.check_short_sale(order, current_position, &mut errors, &mut warnings);
+        }
+        for warning in &warnings {
+            warn!("Order {}: {}", order.order_id, warning);
+        }
+        match errors.into_iter().next() {
+            Some(err) => Err(err.into()),
+            None => Ok(()),
+        }
+    }
+
+    fn reserved(&self, order_id: u64) -> Decimal {
+        self.reservations.get(&order_id).map_or(Decimal::ZERO, |(_, quantity)| *quantity)
+    }
+
+    /// Takes the locates a sent order needs, adjusting what it already holds
+    /// when the order was amended.
+    pub fn reserve(&mut self, order: &Order, current_position: Decimal) -> PyResult<()> {
+        let needed = Self::short_quantity(order, current_position);
+        let reserved = self.reserved(order.order_id);
+        if needed > reserved {
+            self.locates.consume(&order.symbol, needed - reserved)?;
+        } else if needed < reserved {
+            self.locates.release(order.symbol.clone(), reserved - needed);
+        }
+        if needed > Decimal::ZERO {
+            self.reservations.insert(order.order_id, (order.symbol.clone(), needed));
+        } else {
+            self.reservations.remove(&order.order_id);
+        }
+        Ok(())
+    }
+
+    /// Stops tracking the locates of an order that ended, returning those
+    /// of the `unfilled` quantity. The short part of a sale comes last, so
+    /// the unfilled shares are the first to have been covered by locates.
+    pub fn settle(&mut self, order_id: u64, unfilled: Decimal) {
+        if let Some((symbol, quantity)) = self.reservations.remove(&order_id) {
+            let unused = unfilled.min(quantity).max(Decimal::ZERO);
+            if unused > Decimal::ZERO {
+                info!("Returning {} unused locates for {} from order {}", unused, symbol, order_id);
+                self.locates.release(symbol, unused);
+            }
+        }
+    }
+}
+
+#[pymethods]
+impl ComplianceChecker {
+    #[new]
+    #[pyo3(signature = (restricted_list=None, locates=None))]
+    pub fn new(restricted_list: Option<RestrictedList>, locates: Option<LocateInventory>) -> Self {
+        ComplianceChecker {
+            restricted_list: restricted_list.unwrap_or_default(),
+            locates: locates.unwrap_or_default(),
+            reservations: HashMap::new(),
+        }
+    }
+
+    pub fn get_restricted_list(&self) -> RestrictedList {
+        self.restricted_list.clone()
+    }
+
+    pub fn get_locates(&self) -> LocateInventory {
+        self.locates.clone()
+    }
+
+    /// Runs every compliance check without using any locates.
+    #[pyo3(signature = (order, current_position=Decimal::ZERO))]
+    pub fn check(&self, order: &Order, current_position: Decimal) -> ValidationReport {
+        let (errors, warnings) = self.run_checks(order, current_position);
+        ValidationReport::new(order.order_id, errors, warnings)
+    }
+}
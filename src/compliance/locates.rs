@@ -0,0 +1,74 @@
+use pyo3::prelude::*;
+use std::collections::{HashMap, HashSet};
+use std::sync::{Arc, RwLock};
+use log::{info, error};
+use crate::models::numeric::Decimal;
+
+#[derive(Default)]
+struct Inventory {
+    available: HashMap<String, Decimal>,
+    hard_to_borrow: HashSet<String>,
+}
+
+/// Shares available to borrow for short sales, by symbol, and the symbols
+/// that are hard to borrow. Clones share the same inventory.
+#[pyclass]
+#[derive(Clone, Default)]
+pub struct LocateInventory {
+    inventory: Arc<RwLock<Inventory>>,
+}
+
+impl LocateInventory {
+    /// Takes `quantity` out of the inventory; fails without taking anything
+    /// when not enough is available.
+    pub fn consume(&self, symbol: &str, quantity: Decimal) -> PyResult<()> {
+        let mut inventory = self.inventory.write().unwrap();
+        let available = inventory.available.get(symbol).copied().unwrap_or(Decimal::ZERO);
+        if quantity > available {
+            let err_msg = format!("Cannot use {} locates for {}, only {} available", quantity, symbol, available);
+            error!("{}", err_msg);
+            return Err(PyErr::new::<pyo3::exceptions::PyValueError, _>(err_msg));
+        }
+        inventory.available.insert(symbol.to_string(), available - quantity);
+        info!("Used {} locates for {}, {} left", quantity, symbol, available - quantity);
+        Ok(())
+    }
+}
+
+#[pymethods]
+impl LocateInventory {
+    #[new]
+    pub fn new() -> Self {
+        LocateInventory::default()
+    }
+
+    /// Adds newly located shares to what is already available.
+    pub fn add_locate(&self, symbol: String, quantity: Decimal) {
+        let mut inventory = self.inventory.write().unwrap();
+        let available = inventory.available.entry(symbol.clone()).or_insert(Decimal::ZERO);
+        *available += quantity;
+        info!("Added {} locates for {}, {} available", quantity, symbol, available);
+    }
+
+    /// Returns unused locates, e.g. from a short sale that never traded.
+    pub fn release(&self, symbol: String, quantity: Decimal) {
+        self.add_locate(symbol, quantity);
+    }
+
+    pub fn available(&self, symbol: &str) -> Decimal {
+        self.inventory.read().unwrap().available.get(symbol).copied().unwrap_or(Decimal::ZERO)
+    }
+
+    pub fn set_hard_to_borrow(&self, symbol: String, hard_to_borrow: bool) {
+        let mut inventory = self.inventory.write().unwrap();
+        if hard_to_borrow {
+            inventory.hard_to_borrow.insert(symbol);
+        } else {
+            inventory.hard_to_borrow.remove(&symbol);
+        }
+    }
+
+    pub fn is_hard_to_borrow(&self, symbol: &str) -> bool {
+        self.inventory.read().unwrap().hard_to_borrow.contains(symbol)
+    }
+}
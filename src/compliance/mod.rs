@@ -0,0 +1,3 @@
+pub mod restricted_list;
+pub mod locates;
+pub mod compliance_checker;
\ No newline at end of file
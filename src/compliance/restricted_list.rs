@@ -0,0 +1,63 @@
+use pyo3::prelude::*;
+use std::collections::HashMap;
+use std::sync::{Arc, RwLock};
+use log::info;
+
+#[pyclass]
+#[derive(Debug, Clone, Copy, Eq, PartialEq, Hash)]
+pub enum RestrictionLevel {
+    /// No trading at all
+    Restricted,
+    /// Trading allowed, but every order is flagged for review
+    Watch,
+}
+
+#[pyclass]
+#[derive(Debug, Clone)]
+pub struct Restriction {
+    #[pyo3(get)]
+    pub level: RestrictionLevel,
+    #[pyo3(get)]
+    pub reason: Option<String>,
+}
+
+/// Restricted and watch-listed symbols. Clones share the same list so
+/// compliance can update it while the engine is running.
+#[pyclass]
+#[derive(Clone, Default)]
+pub struct RestrictedList {
+    symbols: Arc<RwLock<HashMap<String, Restriction>>>,
+}
+
+#[pymethods]
+impl RestrictedList {
+    #[new]
+    pub fn new() -> Self {
+        RestrictedList {
+            symbols: Arc::new(RwLock::new(HashMap::new())),
+        }
+    }
+
+    #[pyo3(signature = (symbol, level=RestrictionLevel::Restricted, reason=None))]
+    pub fn add(&self, symbol: String, level: RestrictionLevel, reason: Option<String>) {
+        info!("Added {} to the restricted list as {:?}", symbol, level);
+        self.symbols.write().unwrap().insert(symbol, Restriction { level, reason });
+    }
+
+    pub fn remove(&self, symbol: &str) -> bool {
+        info!("Removed {} from the restricted list", symbol);
+        self.symbols.write().unwrap().remove(symbol).is_some()
+    }
+
+    pub fn get(&self, symbol: &str) -> Option<Restriction> {
+        self.symbols.read().unwrap().get(symbol).cloned()
+    }
+
+    pub fn symbols(&self) -> Vec<String> {
+        self.symbols.read().unwrap().keys().cloned().collect()
+    }
+
+    pub fn __len__(&self) -> usize {
+        self.symbols.read().unwrap().len()
+    }
+}
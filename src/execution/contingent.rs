@@ -0,0 +1,179 @@
+use pyo3::prelude::*;
+use std::collections::HashMap;
+use log::{info, error};
+use crate::models::order::Order;
+use crate::models::numeric::Decimal;
+use crate::execution::order_state::{OrderState, OrderStatus};
+
+#[pyclass]
+#[derive(Debug, Clone, Copy, Eq, PartialEq, Hash)]
+pub enum ContingencyType {
+    /// Entry order with a take-profit and a stop-loss that cancel each other
+    Bracket,
+    /// One-cancels-other
+    Oco,
+    /// One-triggers-other
+    Oto,
+}
+
+#[pyclass]
+#[derive(Debug, Clone)]
+pub struct OrderGroup {
+    #[pyo3(get)]
+    pub group_id: u64,
+    #[pyo3(get)]
+    pub contingency_type: ContingencyType,
+    #[pyo3(get)]
+    pub parent_id: Option<u64>,
+    /// Orders held back until the parent fills
+    #[pyo3(get)]
+    pub child_ids: Vec<u64>,
+    /// Orders that cancel each other once one of them trades
+    #[pyo3(get)]
+    pub oco_ids: Vec<u64>,
+    oco_triggered: bool,
+}
+
+/// What the executor has to do after an order in a group changed state.
+#[derive(Debug, Clone)]
+pub enum ContingentAction {
+    Activate(Box<Order>),
+    Cancel(u64),
+    Discard(u64),
+}
+
+#[derive(Default)]
+pub struct ContingentOrderManager {
+    groups: HashMap<u64, OrderGroup>,
+    order_groups: HashMap<u64, u64>,
+    held: HashMap<u64, Order>,
+    next_group_id: u64,
+}
+
+impl ContingentOrderManager {
+    fn add_group(&mut self, contingency_type: ContingencyType, parent_id: Option<u64>, children: Vec<Order>, oco_ids: Vec<u64>) -> u64 {
+        self.next_group_id += 1;
+        let group_id = self.next_group_id;
+
+        let child_ids: Vec<u64> = children.iter().map(|o| o.order_id).collect();
+        for order_id in parent_id.iter().chain(child_ids.iter()).chain(oco_ids.iter()) {
+            self.order_groups.insert(*order_id, group_id);
+        }
+        for child in children {
+            self.held.insert(child.order_id, child);
+        }
+
+        info!("Created {:?} group {} (parent={:?}, children={:?}, oco={:?})", contingency_type, group_id, parent_id, child_ids, oco_ids);
+        self.groups.insert(group_id, OrderGroup {
+            group_id,
+            contingency_type,
+            parent_id,
+            child_ids,
+            oco_ids,
+            oco_triggered: false,
+        });
+        group_id
+    }
+
+    /// Checks that both exits close the entry.
+    pub fn check_bracket(entry: &Order, take_profit: &Order, stop_loss: &Order) -> PyResult<()> {
+        for exit in [take_profit, stop_loss] {
+            if exit.symbol != entry.symbol || exit.side.is_buy() == entry.side.is_buy() || exit.quantity != entry.quantity {
+                let err_msg = format!("Bracket exit order {} must close entry order {} in the same symbol and quantity",
+                                      exit.order_id, entry.order_id);
+                error!("{}", err_msg);
+                return Err(PyErr::new::<pyo3::exceptions::PyValueError, _>(err_msg));
+            }
+        }
+        Ok(())
+    }
+
+    pub fn add_bracket(&mut self, entry: &Order, take_profit: Order, stop_loss: Order) -> PyResult<u64> {
+        Self::check_bracket(entry, &take_profit, &stop_loss)?;
+        let oco_ids = vec![take_profit.order_id, stop_loss.order_id];
+        Ok(self.add_group(ContingencyType::Bracket, Some(entry.order_id), vec![take_profit, stop_loss], oco_ids))
+    }
+
+    pub fn add_oco(&mut self, first: &Order, second: &Order) -> u64 {
+        self.add_group(ContingencyType::Oco, None, Vec::new(), vec![first.order_id, second.order_id])
+    }
+
+    pub fn add_oto(&mut self, parent: &Order, child: Order) -> u64 {
+        self.add_group(ContingencyType::Oto, Some(parent.order_id), vec![child], Vec::new())
+    }
+
+    pub fn is_held(&self, order_id: u64) -> bool {
+        self.held.contains_key(&order_id)
+    }
+
+    pub fn held_orders(&self) -> impl Iterator<Item = &Order> {
+        self.held.values()
+    }
+
+    /// Forgets a group whose orders could not all be sent.
+    pub fn remove_group(&mut self, group_id: u64) {
+        if let Some(group) = self.groups.remove(&group_id) {
+            for order_id in group.parent_id.iter().chain(group.child_ids.iter()).chain(group.oco_ids.iter()) {
+                self.order_groups.remove(order_id);
+                self.held.remove(order_id);
+            }
+        }
+    }
+
+    /// Drops a held child so it will never be activated.
+    pub fn release(&mut self, order_id: u64) -> Option<Order> {
+        self.held.remove(&order_id)
+    }
+
+    pub fn on_update(&mut self, state: &OrderState) -> Vec<ContingentAction> {
+        let mut actions = Vec::new();
+        let group = match self.order_groups.get(&state.order_id).and_then(|id| self.groups.get_mut(id)) {
+            Some(group) => group,
+            None => return actions,
+        };
+
+        if group.parent_id == Some(state.order_id) {
+            match state.status {
+                OrderStatus::Filled => {
+                    for child_id in &group.child_ids {
+                        if let Some(child) = self.held.remove(child_id) {
+                            actions.push(ContingentAction::Activate(Box::new(child)));
+                        }
+                    }
+                },
+                // A parent that dies part-filled still needs its fills protected
+                OrderStatus::Cancelled | OrderStatus::Expired | OrderStatus::Rejected => {
+                    for child_id in &group.child_ids {
+                        if let Some(mut child) = self.held.remove(child_id) {
+                            if state.filled_quantity > Decimal::ZERO {
+                                child.quantity = state.filled_quantity;
+                                actions.push(ContingentAction::Activate(Box::new(child)));
+                            } else {
+                                actions.push(ContingentAction::Discard(*child_id));
+                            }
+                        }
+                    }
+                },
+                _ => {},
+            }
+        } else if group.oco_ids.contains(&state.order_id) && state.filled_quantity > Decimal::ZERO && !group.oco_triggered {
+            group.oco_triggered = true;
+            for sibling in group.oco_ids.iter().filter(|id| **id != state.order_id) {
+                if self.held.remove(sibling).is_some() {
+                    actions.push(ContingentAction::Discard(*sibling));
+                } else {
+                    actions.push(ContingentAction::Cancel(*sibling));
+                }
+            }
+        }
+
+        if !actions.is_empty() {
+            info!("Group {} reacting to order {} ({}): {:?}", group.group_id, state.order_id, state.status, actions);
+        }
+        actions
+    }
+
+    pub fn group_of(&self, order_id: u64) -> Option<OrderGroup> {
+        self.order_groups.get(&order_id).and_then(|id| self.groups.get(id)).cloned()
+    }
+}
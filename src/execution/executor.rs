@@ -1,8 +1,35 @@
 use pyo3::prelude::*;
 use pyo3::types::{PyDict, PyList};
-use std::collections::VecDeque;
+use std::collections::{HashSet, VecDeque};
+use log::{info, error};
 use crate::models::order::{Order, OrderType};
+use crate::models::numeric::Decimal;
 use crate::validation::order_validator::OrderValidator;
+use crate::risk::risk_validator::RiskValidator;
+use crate::risk::position_ledger::PositionLedger;
+use crate::risk::pnl_engine::{CostBasis, PnlEngine};
+use crate::execution::order_state::{expire_result, ExecType, ExecutionReport, OrderState, OrderStateTracker, OrderStatus};
+use crate::execution::contingent::{ContingentAction, ContingentOrderManager, OrderGroup};
+use crate::execution::self_trade::{SelfTradeAction, SelfTradePrevention};
+use crate::execution::throttle::OrderThrottle;
+use crate::validation::error::ValidationError;
+use crate::calendar::clock::ClockHandle;
+use crate::compliance::compliance_checker::ComplianceChecker;
+
+/// An amendment that has passed its checks and only has to be sent.
+struct Amendment {
+    amended: Order,
+    // What is left of the amended order once the fills so far are taken off
+    remaining: Order,
+    leaves_quantity: Decimal,
+    sellable_position: Decimal,
+}
+
+/// What self-trade prevention does to a resting order, checked up front.
+enum RestingAction {
+    Cancel(u64),
+    Reduce(Box<Amendment>),
+}
 
 #[pyclass]
 pub struct OrderExecutor {
@@ -10,16 +37,341 @@ pub struct OrderExecutor {
     #[pyo3(get)]
     python_callback: PyObject,
     order_queue: VecDeque<Order>,
+    risk_validator: Option<RiskValidator>,
+    tracker: OrderStateTracker,
+    client_order_ids: HashSet<String>,
+    contingent: ContingentOrderManager,
+    // Broker responses to requests the engine sent on its own, e.g. bracket exits
+    contingent_results: Vec<PyObject>,
+    clock: ClockHandle,
+    compliance: Option<ComplianceChecker>,
+    self_trade: Option<SelfTradePrevention>,
+    throttle: Option<OrderThrottle>,
+    ledger: PositionLedger,
+    pnl: PnlEngine,
+}
+
+impl OrderExecutor {
+    fn check_client_order_id(&self, order: &Order) -> PyResult<()> {
+        if let Some(client_order_id) = &order.client_order_id {
+            let queued = self.order_queue.iter()
+                .chain(self.contingent.held_orders())
+                .any(|o| o.client_order_id.as_ref() == Some(client_order_id));
+            if queued || self.client_order_ids.contains(client_order_id) {
+                let err_msg = format!("Duplicate client order id: {}", client_order_id);
+                error!("{}", err_msg);
+                return Err(PyErr::new::<pyo3::exceptions::PyValueError, _>(err_msg));
+            }
+        }
+        Ok(())
+    }
+
+    fn submit(&mut self, py: Python, order: &Order, current_position: Option<Decimal>) -> PyResult<PyObject> {
+        // Refused up front, before self-trade prevention can touch resting orders
+        if self.tracker.get_state(order.order_id).is_some() {
+            let err_msg = format!("Order {} has already been sent", order.order_id);
+            error!("{}", err_msg);
+            return Err(PyErr::new::<pyo3::exceptions::PyValueError, _>(err_msg));
+        }
+        if self.contingent.is_held(order.order_id) {
+            let err_msg = format!("Order {} is held by a contingent group and is sent by the engine", order.order_id);
+            error!("{}", err_msg);
+            return Err(PyErr::new::<pyo3::exceptions::PyValueError, _>(err_msg));
+        }
+        self.check_client_order_id(order)?;
+        let current_position = current_position.unwrap_or_else(|| self.position_of(order));
+        self.send(py, order, current_position, true)
+    }
+
+    /// Sends an order to the broker, whether a strategy or the engine asked
+    /// for it; a bracket exit or OTO child needs locates like any short sale.
+    /// Only `throttled` orders count against the rate limits, only once every
+    /// other check has passed, and not at all if the order isn't sent.
+    fn send(&mut self, py: Python, requested: &Order, current_position: Decimal, throttled: bool) -> PyResult<PyObject> {
+        let mut order = self.validator.conform(requested)?;
+        let sellable_position = self.sellable_position(&order, current_position);
+        if let Some(compliance) = &self.compliance {
+            compliance.enforce(&order, Some(sellable_position))?;
+        }
+        let prepared_order = self.prepare_order(py, &order)?;
+
+        let mut resting_actions = Vec::new();
+        if let Some(self_trade) = &self.self_trade {
+            let (quantity, actions) = self_trade.resolve(&order, |order_id| self.tracker.get_state(order_id).is_some())?;
+            for action in actions {
+                resting_actions.push(match action {
+                    SelfTradeAction::Cancel(order_id) => RestingAction::Cancel(order_id),
+                    SelfTradeAction::Reduce(order_id, quantity) => {
+                        let price = self.open_state(order_id)?.order.price;
+                        RestingAction::Reduce(Box::new(self.check_amendment(py, order_id, quantity, price, None, Some(current_position), None)?))
+                    },
+                });
+            }
+            if quantity != order.quantity {
+                info!("Order {} netted down from {} to {}", order.order_id, order.quantity, quantity);
+                order.quantity = quantity;
+                prepared_order.as_ref(py).set_item("quantity", quantity)?;
+            }
+        }
+        let order = &order;
+
+        if let (Some(risk_validator), false) = (&self.risk_validator, order.quantity.is_zero()) {
+            risk_validator.check_order(order, Some(current_position), None).into_result(py)?;
+        }
+        let throttle = self.throttle.clone().filter(|_| throttled);
+        if let Some(throttle) = &throttle {
+            throttle.admit(requested, self.clock.now())?;
+        }
+        // A token taken for an order that never went out is handed back
+        self.dispatch(py, order, prepared_order, resting_actions, sellable_position)
+            .inspect_err(|_| if let Some(throttle) = &throttle { throttle.release(requested) })
+    }
+
+    /// Sends a checked order, first running the self-trade actions it needs.
+    fn dispatch(&mut self, py: Python, order: &Order, prepared_order: PyObject, resting_actions: Vec<RestingAction>,
+                sellable_position: Decimal) -> PyResult<PyObject> {
+        // Resting orders are only touched once the incoming order is known to be good
+        for action in resting_actions {
+            self.run_resting_action(py, action)?;
+        }
+        if order.quantity.is_zero() {
+            info!("Order {} fully netted against resting orders and not sent", order.order_id);
+            return Ok(py.None());
+        }
+        if let Some(compliance) = &mut self.compliance {
+            compliance.reserve(order, sellable_position)?;
+        }
+
+        // Record the order before handing it to the broker so a retry after a
+        // timeout is refused rather than sent twice
+        self.tracker.track(order.clone())?;
+        self.tracker.apply_report(order.order_id, &ExecutionReport::new(ExecType::PendingNew, Decimal::ZERO, Decimal::ZERO, None, None))?;
+        self.ledger.set_pending(order, order.quantity);
+        self.publish(order.order_id);
+        if let Some(client_order_id) = &order.client_order_id {
+            self.client_order_ids.insert(client_order_id.clone());
+        }
+        info!("Submitting order {}", order.order_id);
+
+        self.python_callback.call1(py, (prepared_order,))
+    }
+
+    /// Runs an amendment of a working order through the same checks as a new
+    /// order, without sending anything.
+    #[allow(clippy::too_many_arguments)]
+    fn check_amendment(&self, py: Python, order_id: u64, new_quantity: Decimal, new_price: Decimal, new_stop_price: Option<Decimal>,
+                       current_position: Option<Decimal>, daily_pnl: Option<Decimal>) -> PyResult<Amendment> {
+        let state = self.open_state(order_id)?;
+        let current_position = current_position.unwrap_or_else(|| self.position_of(&state.order));
+        let mut amended = state.order.clone();
+        amended.amend(new_quantity, new_price, new_stop_price);
+        // The amendment is a fresh request, so it must not trip the max order age check
+        amended.timestamp = self.clock.now();
+        let amended = self.validator.conform(&amended)?;
+
+        self.validator.validate(&amended)?;
+        let mut remaining = amended.clone();
+        remaining.quantity = amended.quantity - state.filled_quantity;
+        let sellable_position = self.sellable_position(&remaining, current_position);
+        if let Some(compliance) = &self.compliance {
+            compliance.enforce(&remaining, Some(sellable_position))?;
+        }
+        // An amendment never cancels or nets other orders, whatever the policy
+        if let Some(resting_order_id) = self.self_trade.as_ref().and_then(|self_trade| self_trade.crossing(&amended)) {
+            return Err(ValidationError::SelfTrade { symbol: amended.symbol.clone(), resting_order_id }.into());
+        }
+        if let Some(risk_validator) = &self.risk_validator {
+            risk_validator.check_order(&remaining, Some(current_position), daily_pnl).into_result(py)?;
+        }
+        Ok(Amendment { amended, remaining, leaves_quantity: state.leaves_quantity(), sellable_position })
+    }
+
+    /// Sends a checked amendment. Amendments the engine makes on its own are
+    /// not `throttled`.
+    fn send_amendment(&mut self, py: Python, amendment: Amendment, throttled: bool) -> PyResult<PyObject> {
+        let throttle = self.throttle.clone().filter(|_| throttled);
+        if let Some(throttle) = &throttle {
+            throttle.consume(&amendment.amended, self.clock.now())?;
+        }
+        let amended = amendment.amended.clone();
+        self.request_amendment(py, amendment)
+            .inspect_err(|_| if let Some(throttle) = &throttle { throttle.release(&amended) })
+    }
+
+    fn request_amendment(&mut self, py: Python, amendment: Amendment) -> PyResult<PyObject> {
+        let Amendment { amended, remaining, leaves_quantity, sellable_position } = amendment;
+        let order_id = amended.order_id;
+        if let Some(compliance) = &mut self.compliance {
+            compliance.reserve(&remaining, sellable_position)?;
+        }
+        self.tracker.request_replace(order_id, amended.quantity, amended.price, amended.stop_price)?;
+        // Until the broker answers either quantity may end up working
+        self.ledger.set_pending(&amended, remaining.quantity.max(leaves_quantity));
+        self.publish(order_id);
+        let request = self.prepare_order(py, &amended)?;
+        request.as_ref(py).set_item("request_type", "replace")?;
+        info!("Requesting replace of order {}: quantity={}, price={}", order_id, amended.quantity, amended.price);
+
+        self.python_callback.call1(py, (request,))
+    }
+
+    /// Runs every action of a group even if some fail, then raises one error
+    /// for the failures. Children that were never sent are handed back in
+    /// its `failed_orders` so they can be sent again or dropped.
+    fn run_contingent_actions(&mut self, py: Python, actions: Vec<ContingentAction>) -> PyResult<()> {
+        let mut messages = Vec::new();
+        let mut failed_orders = Vec::new();
+        for action in actions {
+            let child = match &action {
+                ContingentAction::Activate(order) => Some(order.as_ref().clone()),
+                _ => None,
+            };
+            if let Err(err) = self.run_contingent_action(py, action) {
+                error!("Contingent action failed: {}", err);
+                messages.push(err.to_string());
+                failed_orders.extend(child.filter(|order| self.tracker.get_state(order.order_id).is_none()));
+            }
+        }
+        if messages.is_empty() {
+            return Ok(());
+        }
+        let err = PyErr::new::<pyo3::exceptions::PyValueError, _>(format!("Contingent orders failed: {}", messages.join("; ")));
+        err.value(py).setattr("failed_orders", failed_orders.into_py(py))?;
+        Err(err)
+    }
+
+    fn run_contingent_action(&mut self, py: Python, action: ContingentAction) -> PyResult<()> {
+        match action {
+            ContingentAction::Activate(mut order) => {
+                info!("Activating contingent order {}", order.order_id);
+                // The child is only entered now, however long the parent took to fill
+                order.timestamp = self.clock.now();
+                let current_position = self.position_of(&order);
+                let result = self.send(py, &order, current_position, false)?;
+                self.contingent_results.push(result);
+            },
+            ContingentAction::Cancel(order_id) => {
+                if self.tracker.get_state(order_id).is_some_and(|s| s.is_open()) {
+                    let result = self.cancel(py, order_id)?;
+                    self.contingent_results.push(result);
+                }
+            },
+            ContingentAction::Discard(order_id) => {
+                self.ledger.unlink(order_id);
+                info!("Discarded contingent order {} before it was sent", order_id);
+            },
+        }
+        Ok(())
+    }
+
+    fn run_resting_action(&mut self, py: Python, action: RestingAction) -> PyResult<()> {
+        let result = match action {
+            RestingAction::Cancel(order_id) => {
+                info!("Cancelling order {} to prevent a self-trade", order_id);
+                self.cancel(py, order_id)?
+            },
+            RestingAction::Reduce(amendment) => {
+                info!("Reducing order {} to {} to prevent a self-trade", amendment.amended.order_id, amendment.amended.quantity);
+                self.send_amendment(py, *amendment, false)?
+            },
+        };
+        self.contingent_results.push(result);
+        Ok(())
+    }
+
+    /// The account's position in the order's symbol across its strategies.
+    fn position_of(&self, order: &Order) -> Decimal {
+        self.ledger.net_position(&order.symbol, order.account.as_deref())
+    }
+
+    /// The position left once the account's other working sales fill, which
+    /// is all a new sale can close before it goes short.
+    fn sellable_position(&self, order: &Order, current_position: Decimal) -> Decimal {
+        let (_, pending_sells) = self.ledger.pending_excluding(&order.symbol, order.account.as_deref(), Some(order.order_id));
+        current_position - pending_sells
+    }
+
+    /// Brings the ledger's working quantity and the locates in line with the
+    /// order's new state.
+    fn on_expired(&mut self, order_ids: &[u64]) {
+        for order_id in order_ids {
+            if let Some(state) = self.tracker.get_state(*order_id) {
+                self.on_state_change(&state);
+            }
+        }
+    }
+
+    /// Shares an order's latest state with the other executors that use the
+    /// same self-trade prevention.
+    fn publish(&self, order_id: u64) {
+        if let (Some(self_trade), Some(state)) = (&self.self_trade, self.tracker.get_state(order_id)) {
+            self_trade.update(&state);
+        }
+    }
+
+    fn on_state_change(&mut self, state: &OrderState) {
+        self.ledger.set_pending(&state.order, state.leaves_quantity());
+        if let Some(self_trade) = &self.self_trade {
+            self_trade.update(state);
+        }
+        if let Some(compliance) = &mut self.compliance {
+            // Locates of the part that never traded go back to the inventory
+            if state.status.is_terminal() {
+                compliance.settle(state.order_id, state.order.quantity - state.filled_quantity);
+            }
+        }
+    }
+
+    /// Checks every leg of a group up front so that a bad exit order can't
+    /// leave an entry working without its protection.
+    fn check_group(&self, orders: &[&Order]) -> PyResult<()> {
+        for order in orders {
+            self.check_client_order_id(order)?;
+            self.validator.validate(order)?;
+        }
+        Ok(())
+    }
+
+    fn open_state(&self, order_id: u64) -> PyResult<OrderState> {
+        let state = self.tracker.get_state(order_id).ok_or_else(|| {
+            PyErr::new::<pyo3::exceptions::PyKeyError, _>(format!("Unknown order: {}", order_id))
+        })?;
+        if !state.is_open() {
+            let err_msg = format!("Order {} is {} and can no longer be amended", order_id, state.status);
+            error!("{}", err_msg);
+            return Err(PyErr::new::<pyo3::exceptions::PyValueError, _>(err_msg));
+        }
+        Ok(state)
+    }
 }
 
 #[pymethods]
 impl OrderExecutor {
     #[new]
-    fn new(validator: OrderValidator, python_callback: PyObject) -> Self {
+    #[pyo3(signature = (validator, python_callback, risk_validator=None))]
+    fn new(validator: OrderValidator, python_callback: PyObject, risk_validator: Option<RiskValidator>) -> Self {
+        let ledger = PositionLedger::new();
+        let pnl = PnlEngine::with_clock(CostBasis::Fifo, validator.get_clock());
+        let risk_validator = risk_validator.map(|mut r| {
+            r.set_ledger(Some(ledger.clone()));
+            r.set_pnl(Some(pnl.clone()));
+            r
+        });
         OrderExecutor {
+            clock: validator.get_clock(),
             validator,
             python_callback,
             order_queue: VecDeque::new(),
+            risk_validator,
+            tracker: OrderStateTracker::new(),
+            client_order_ids: HashSet::new(),
+            contingent: ContingentOrderManager::default(),
+            contingent_results: Vec::new(),
+            compliance: None,
+            self_trade: None,
+            throttle: None,
+            ledger,
+            pnl,
         }
     }
 
@@ -29,17 +381,45 @@ impl OrderExecutor {
 
         // Prepare order data for Python
         let order_dict = PyDict::new(py);
+        order_dict.set_item("request_type", "new")?;
+        order_dict.set_item("order_id", order.order_id)?;
+        if let Some(client_order_id) = &order.client_order_id {
+            order_dict.set_item("client_order_id", client_order_id)?;
+        }
+        if let Some(account) = &order.account {
+            order_dict.set_item("account", account)?;
+        }
         order_dict.set_item("symbol", &order.symbol)?;
+        order_dict.set_item("side", order.side.to_string())?;
         order_dict.set_item("quantity", order.quantity)?;
         order_dict.set_item("order_type", order.order_type.to_string())?;
+        order_dict.set_item("time_in_force", order.time_in_force.to_string())?;
+        if order.extended_hours {
+            order_dict.set_item("extended_hours", true)?;
+        }
+        if let Some(expire_time) = order.expire_time {
+            order_dict.set_item("expire_time", expire_time)?;
+        }
         
         // Add optional fields based on order type
         match order.order_type {
-            OrderType::Market => {},
-            OrderType::Limit | OrderType::Stop | OrderType::StopLimit => {
+            OrderType::Market | OrderType::TrailingStop => {},
+            OrderType::Limit | OrderType::Stop | OrderType::StopLimit | OrderType::TrailingStopLimit => {
                 order_dict.set_item("price", order.price)?;
             },
         }
+        if let Some(limit_price) = order.limit_price {
+            order_dict.set_item("limit_price", limit_price)?;
+        }
+        if let Some(stop_price) = order.stop_price {
+            order_dict.set_item("stop_price", stop_price)?;
+        }
+        if let Some(trail_amount) = order.trail_amount {
+            order_dict.set_item("trail_amount", trail_amount)?;
+        }
+        if let Some(trail_percent) = order.trail_percent {
+            order_dict.set_item("trail_percent", trail_percent)?;
+        }
 
         // Add any additional fields
         if let Some(additional_data) = &order.additional_data {
@@ -51,7 +431,104 @@ impl OrderExecutor {
         Ok(order_dict.into())
     }
 
+    /// Checks every new order and amendment before it is sent. The risk
+    /// validator reads positions and working orders from the executor's
+    /// ledger and daily PnL from its PnL engine.
+    fn set_risk_validator(&mut self, risk_validator: Option<RiskValidator>) {
+        self.risk_validator = risk_validator.map(|mut r| {
+            r.set_ledger(Some(self.ledger.clone()));
+            r.set_pnl(Some(self.pnl.clone()));
+            r
+        });
+    }
+
+    fn get_pnl(&self) -> PnlEngine {
+        self.pnl.clone()
+    }
+
+    /// Replaces the executor's PnL engine, e.g. to choose a cost basis or
+    /// share one engine between executors.
+    fn set_pnl(&mut self, pnl: PnlEngine) {
+        if let Some(risk_validator) = &mut self.risk_validator {
+            risk_validator.set_pnl(Some(pnl.clone()));
+        }
+        self.pnl = pnl;
+    }
+
+    fn get_ledger(&self) -> PositionLedger {
+        self.ledger.clone()
+    }
+
+    /// Shares a ledger between executors, e.g. one per strategy of the same
+    /// account. Set it before sending orders.
+    fn set_ledger(&mut self, ledger: PositionLedger) {
+        if let Some(risk_validator) = &mut self.risk_validator {
+            risk_validator.set_ledger(Some(ledger.clone()));
+        }
+        self.ledger = ledger;
+    }
+
+    fn set_compliance(&mut self, compliance: Option<ComplianceChecker>) {
+        self.compliance = compliance;
+    }
+
+    /// Rate-limits new orders and amendments the strategies send; orders the
+    /// engine sends on its own, such as bracket exits, are never throttled.
+    fn set_throttle(&mut self, throttle: Option<OrderThrottle>) {
+        self.throttle = throttle;
+    }
+
+    /// Checks every order against the working orders of the same account
+    /// before it is sent. Executors that share one `SelfTradePrevention` see
+    /// each other's orders; crossing another executor's order is refused
+    /// whatever the policy, since only its own executor can amend it.
+    fn set_self_trade_prevention(&mut self, self_trade: Option<SelfTradePrevention>) {
+        if let Some(self_trade) = &self_trade {
+            for state in self.tracker.open_orders() {
+                self_trade.update(&state);
+            }
+        }
+        self.self_trade = self_trade;
+    }
+
+    fn cancel(&mut self, py: Python, order_id: u64) -> PyResult<PyObject> {
+        // Children that were never sent only need to be dropped
+        if self.contingent.release(order_id).is_some() {
+            self.ledger.unlink(order_id);
+            info!("Cancelled held contingent order {}", order_id);
+            return Ok(py.None());
+        }
+
+        let state = self.open_state(order_id)?;
+        self.tracker.apply_report(order_id, &ExecutionReport::new(ExecType::PendingCancel, Decimal::ZERO, Decimal::ZERO, None, None))?;
+        self.publish(order_id);
+
+        let request = PyDict::new(py);
+        request.set_item("request_type", "cancel")?;
+        request.set_item("order_id", order_id)?;
+        if let Some(client_order_id) = &state.order.client_order_id {
+            request.set_item("client_order_id", client_order_id)?;
+        }
+        request.set_item("symbol", &state.order.symbol)?;
+        request.set_item("side", state.order.side.to_string())?;
+        info!("Requesting cancel of order {}", order_id);
+
+        self.python_callback.call1(py, (request,))
+    }
+
+    /// Amends a working order's quantity, price and, for stop orders, its
+    /// `new_stop_price`. Without a `current_position` the account's position
+    /// comes from the ledger, and without a `daily_pnl` from the PnL engine.
+    #[pyo3(signature = (order_id, new_quantity, new_price, current_position=None, daily_pnl=None, new_stop_price=None))]
+    #[allow(clippy::too_many_arguments)]
+    fn replace(&mut self, py: Python, order_id: u64, new_quantity: Decimal, new_price: Decimal, current_position: Option<Decimal>, daily_pnl: Option<Decimal>,
+               new_stop_price: Option<Decimal>) -> PyResult<PyObject> {
+        let amendment = self.check_amendment(py, order_id, new_quantity, new_price, new_stop_price, current_position, daily_pnl)?;
+        self.send_amendment(py, amendment, true)
+    }
+
     fn queue_order(&mut self, order: Order) -> PyResult<()> {
+        self.check_client_order_id(&order)?;
         self.order_queue.push_back(order);
         Ok(())
     }
@@ -59,25 +536,149 @@ impl OrderExecutor {
     fn execute_queued(&mut self, py: Python) -> PyResult<PyObject> {
         let mut results = Vec::new();
         while let Some(order) = self.order_queue.pop_front() {
-            let prepared_order = self.prepare_order(py, &order)?;
-            let result = self.python_callback.call1(py, (prepared_order,))?;
+            let result = self.submit(py, &order, None)?;
             results.push(result);
         }
         Ok(PyList::new(py, results).into())
     }
 
-    fn execute(&self, py: Python, order: &Order) -> PyResult<PyObject> {
-        let prepared_order = self.prepare_order(py, order)?;
-        self.python_callback.call1(py, (prepared_order,))
+    /// `current_position` is the account's position in the symbol, used to
+    /// tell a sale that closes a long from one that goes short. It defaults
+    /// to the position in the ledger.
+    #[pyo3(signature = (order, current_position=None))]
+    fn execute(&mut self, py: Python, order: &Order, current_position: Option<Decimal>) -> PyResult<PyObject> {
+        self.submit(py, order, current_position)
     }
 
-    fn execute_multiple(&self, py: Python, orders: &PyList) -> PyResult<PyObject> {
+    fn execute_multiple(&mut self, py: Python, orders: &PyList) -> PyResult<PyObject> {
         let mut results = Vec::new();
         for order in orders.iter() {
             let order: Order = order.extract()?;
-            let result = self.execute(py, &order)?;
+            let result = self.execute(py, &order, None)?;
             results.push(result);
         }
         Ok(PyList::new(py, results).into())
     }
+
+    /// Submits the entry order and holds the take-profit and stop-loss until
+    /// it fills; the exits then cancel each other. The group only exists
+    /// once the entry has been sent.
+    #[pyo3(signature = (entry, take_profit, stop_loss, current_position=None))]
+    fn submit_bracket(&mut self, py: Python, entry: &Order, take_profit: Order, stop_loss: Order, current_position: Option<Decimal>) -> PyResult<PyObject> {
+        self.check_group(&[entry, &take_profit, &stop_loss])?;
+        ContingentOrderManager::check_bracket(entry, &take_profit, &stop_loss)?;
+        let result = self.submit(py, entry, current_position)?;
+        let oco_ids = [take_profit.order_id, stop_loss.order_id];
+        let group_id = self.contingent.add_bracket(entry, take_profit, stop_loss)?;
+        self.ledger.link(group_id, &oco_ids);
+        Ok(result)
+    }
+
+    /// Submits both orders; the first one to trade cancels the other. If the
+    /// second can't be sent the first is left working on its own.
+    #[pyo3(signature = (first, second, current_position=None))]
+    fn submit_oco(&mut self, py: Python, first: &Order, second: &Order, current_position: Option<Decimal>) -> PyResult<PyObject> {
+        self.check_group(&[first, second])?;
+        // Linked up front so the second order isn't checked as if both could trade
+        let group_id = self.contingent.add_oco(first, second);
+        self.ledger.link(group_id, &[first.order_id, second.order_id]);
+        let results = self.submit(py, first, current_position)
+            .and_then(|first_result| Ok(vec![first_result, self.submit(py, second, current_position)?]))
+            .inspect_err(|_| {
+                self.contingent.remove_group(group_id);
+                self.ledger.unlink(first.order_id);
+                self.ledger.unlink(second.order_id);
+            })?;
+        Ok(PyList::new(py, results).into())
+    }
+
+    /// Submits the parent and sends the child once the parent has filled.
+    #[pyo3(signature = (parent, child, current_position=None))]
+    fn submit_oto(&mut self, py: Python, parent: &Order, child: Order, current_position: Option<Decimal>) -> PyResult<PyObject> {
+        self.check_group(&[parent, &child])?;
+        let result = self.submit(py, parent, current_position)?;
+        self.contingent.add_oto(parent, child);
+        Ok(result)
+    }
+
+    fn apply_report(&mut self, py: Python, order_id: u64, report: &ExecutionReport) -> PyResult<OrderStatus> {
+        // The tracker, PnL and ledger take the report together or not at all
+        self.tracker.check_report(order_id, report)?;
+        if let (ExecType::Fill, Some(state)) = (report.exec_type, self.tracker.get_state(order_id)) {
+            PnlEngine::check_fill(report.last_quantity, report.timestamp)?;
+            // The ledger is the one book that can still refuse the fill
+            self.ledger.apply_fill(&state.order, report.last_quantity, report.last_price)?;
+        }
+        let status = self.tracker.apply_report(order_id, report)?;
+        if let Some(state) = self.tracker.get_state(order_id) {
+            if report.exec_type == ExecType::Fill {
+                self.pnl.apply_fill(&state.order, report.last_quantity, report.last_price, report.timestamp)?;
+            }
+            self.on_state_change(&state);
+            let actions = self.contingent.on_update(&state);
+            self.run_contingent_actions(py, actions)?;
+        }
+        Ok(status)
+    }
+
+    fn get_order_group(&self, order_id: u64) -> Option<OrderGroup> {
+        self.contingent.group_of(order_id)
+    }
+
+    fn get_held_orders(&self) -> Vec<Order> {
+        self.contingent.held_orders().cloned().collect()
+    }
+
+    /// Returns and clears the broker responses to requests the engine sent on
+    /// its own behalf. Async adapters should await these.
+    fn take_contingent_results(&mut self, py: Python) -> PyObject {
+        let results = std::mem::take(&mut self.contingent_results);
+        PyList::new(py, results).into()
+    }
+
+    fn get_order_state(&self, order_id: u64) -> Option<OrderState> {
+        self.tracker.get_state(order_id)
+    }
+
+    fn get_order_state_by_client_id(&self, client_order_id: &str) -> Option<OrderState> {
+        self.tracker.find_by_client_order_id(client_order_id)
+    }
+
+    /// Uses the clock for the executor, its validator and its PnL engine, so
+    /// a backtest can drive them all from one simulated clock.
+    fn set_clock(&mut self, clock: ClockHandle) -> PyResult<()> {
+        self.validator.set_clock(clock.clone())?;
+        self.pnl.set_clock(clock.clone());
+        self.clock = clock;
+        Ok(())
+    }
+
+    fn get_clock(&self) -> ClockHandle {
+        self.clock.clone()
+    }
+
+    /// Expires GTD orders that have run past their expire time, as of
+    /// `timestamp` or the clock's current time. Nothing expires on its own,
+    /// even with a clock and calendar set: the caller must call this, e.g.
+    /// on a timer or as a backtest advances its clock.
+    #[pyo3(signature = (timestamp=None))]
+    fn expire_orders(&mut self, py: Python, timestamp: Option<f64>) -> PyResult<Vec<u64>> {
+        let (expired, failures) = self.tracker.expire_due(timestamp.unwrap_or_else(|| self.clock.now()), false);
+        self.on_expired(&expired);
+        expire_result(py, expired, failures)
+    }
+
+    /// Expires Day, OPG and CLS orders along with any due GTD orders. The
+    /// engine doesn't watch the calendar for the close, so the caller must
+    /// call this once each session has closed.
+    #[pyo3(signature = (timestamp=None))]
+    fn on_session_close(&mut self, py: Python, timestamp: Option<f64>) -> PyResult<Vec<u64>> {
+        let (expired, failures) = self.tracker.expire_due(timestamp.unwrap_or_else(|| self.clock.now()), true);
+        self.on_expired(&expired);
+        expire_result(py, expired, failures)
+    }
+
+    fn open_orders(&self) -> Vec<OrderState> {
+        self.tracker.open_orders()
+    }
 }
\ No newline at end of file
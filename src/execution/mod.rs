@@ -1 +1,5 @@
-pub mod executor;
\ No newline at end of file
+pub mod executor;
+pub mod order_state;
+pub mod contingent;
+pub mod self_trade;
+pub mod throttle;
\ No newline at end of file
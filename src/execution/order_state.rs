@@ -0,0 +1,409 @@
+use pyo3::prelude::*;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fmt;
+use log::{info, error};
+use crate::models::order::{Order, TimeInForce};
+use crate::models::numeric::{notional, Decimal};
+
+#[pyclass]
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, Eq, PartialEq, Hash)]
+pub enum OrderStatus {
+    New,
+    PendingNew,
+    Accepted,
+    PartiallyFilled,
+    Filled,
+    PendingCancel,
+    PendingReplace,
+    Cancelled,
+    Rejected,
+    Expired,
+}
+
+impl OrderStatus {
+    pub fn is_terminal(&self) -> bool {
+        matches!(self, OrderStatus::Filled | OrderStatus::Cancelled | OrderStatus::Rejected | OrderStatus::Expired)
+    }
+
+    /// Waiting on the broker to answer a request.
+    pub fn is_pending(&self) -> bool {
+        matches!(self, OrderStatus::PendingNew | OrderStatus::PendingCancel | OrderStatus::PendingReplace)
+    }
+
+    pub fn can_transition_to(&self, next: OrderStatus) -> bool {
+        use OrderStatus::*;
+        matches!((*self, next),
+            (New, PendingNew) | (New, Rejected) | (New, Cancelled)
+            | (PendingNew, Accepted) | (PendingNew, Rejected) | (PendingNew, PendingCancel)
+            | (PendingNew, PartiallyFilled) | (PendingNew, Filled) | (PendingNew, Expired)
+            | (Accepted, PartiallyFilled) | (Accepted, Filled) | (Accepted, PendingCancel)
+            | (Accepted, Cancelled) | (Accepted, Expired)
+            | (PartiallyFilled, PartiallyFilled) | (PartiallyFilled, Filled) | (PartiallyFilled, PendingCancel)
+            | (PartiallyFilled, Cancelled) | (PartiallyFilled, Expired)
+            // Fills can still arrive while a cancel is in flight, and a rejected
+            // cancel puts the order back where it was
+            | (PendingCancel, PendingCancel) | (PendingCancel, Filled) | (PendingCancel, Cancelled)
+            | (PendingCancel, Expired) | (PendingCancel, PendingNew) | (PendingCancel, Accepted)
+            | (PendingCancel, PartiallyFilled)
+            | (Accepted, PendingReplace) | (PartiallyFilled, PendingReplace)
+            | (PendingReplace, PendingReplace) | (PendingReplace, Accepted) | (PendingReplace, PartiallyFilled)
+            | (PendingReplace, Filled) | (PendingReplace, PendingCancel) | (PendingReplace, Cancelled)
+            | (PendingReplace, Expired))
+    }
+}
+
+impl fmt::Display for OrderStatus {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{:?}", self)
+    }
+}
+
+#[pyclass]
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, Eq, PartialEq, Hash)]
+pub enum ExecType {
+    PendingNew,
+    Accepted,
+    Fill,
+    Rejected,
+    PendingCancel,
+    Cancelled,
+    Replaced,
+    /// Rejection of a cancel or a cancel/replace request
+    CancelRejected,
+    Expired,
+}
+
+#[pyclass]
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct ExecutionReport {
+    #[pyo3(get, set)]
+    pub exec_type: ExecType,
+    #[pyo3(get, set)]
+    pub last_quantity: Decimal,
+    #[pyo3(get, set)]
+    pub last_price: Decimal,
+    #[pyo3(get, set)]
+    pub reason: Option<String>,
+    #[pyo3(get, set)]
+    pub timestamp: Option<f64>,
+}
+
+#[pymethods]
+impl ExecutionReport {
+    #[new]
+    #[pyo3(signature = (exec_type, last_quantity=Decimal::ZERO, last_price=Decimal::ZERO, reason=None, timestamp=None))]
+    pub fn new(exec_type: ExecType, last_quantity: Decimal, last_price: Decimal, reason: Option<String>, timestamp: Option<f64>) -> Self {
+        ExecutionReport {
+            exec_type,
+            last_quantity,
+            last_price,
+            reason,
+            timestamp,
+        }
+    }
+
+    pub fn __str__(&self) -> PyResult<String> {
+        Ok(format!("ExecutionReport(exec_type={:?}, last_quantity={}, last_price={}, reason={:?})",
+                   self.exec_type, self.last_quantity, self.last_price, self.reason))
+    }
+}
+
+#[pyclass]
+#[derive(Debug, Clone)]
+pub struct OrderState {
+    #[pyo3(get)]
+    pub order_id: u64,
+    #[pyo3(get)]
+    pub order: Order,
+    #[pyo3(get)]
+    pub status: OrderStatus,
+    #[pyo3(get)]
+    pub filled_quantity: Decimal,
+    #[pyo3(get)]
+    pub avg_fill_price: Decimal,
+    #[pyo3(get)]
+    pub reject_reason: Option<String>,
+    // Sum of fill quantity times price, kept so the average never drifts
+    fill_notional: Decimal,
+    // Status to fall back to when a cancel or replace request is rejected
+    prior_status: Option<OrderStatus>,
+    // Quantity, price and stop price requested by an in-flight cancel/replace
+    pending_amendment: Option<(Decimal, Decimal, Option<Decimal>)>,
+}
+
+impl OrderState {
+    pub fn new(order: Order) -> Self {
+        OrderState {
+            order_id: order.order_id,
+            order,
+            status: OrderStatus::New,
+            filled_quantity: Decimal::ZERO,
+            avg_fill_price: Decimal::ZERO,
+            reject_reason: None,
+            fill_notional: Decimal::ZERO,
+            prior_status: None,
+            pending_amendment: None,
+        }
+    }
+
+    /// Status an open order returns to once nothing is pending against it.
+    fn working_status(&self) -> OrderStatus {
+        if self.filled_quantity >= self.order.quantity {
+            OrderStatus::Filled
+        } else if self.filled_quantity > Decimal::ZERO {
+            OrderStatus::PartiallyFilled
+        } else {
+            OrderStatus::Accepted
+        }
+    }
+
+    pub fn request_replace(&mut self, quantity: Decimal, price: Decimal, stop_price: Option<Decimal>) -> PyResult<()> {
+        if quantity < self.filled_quantity {
+            let err_msg = format!("Cannot replace order {} with quantity {} below the {} already filled",
+                                  self.order_id, quantity, self.filled_quantity);
+            error!("{}", err_msg);
+            return Err(PyErr::new::<pyo3::exceptions::PyValueError, _>(err_msg));
+        }
+        self.enter_pending(OrderStatus::PendingReplace)?;
+        self.pending_amendment = Some((quantity, price, stop_price));
+        Ok(())
+    }
+
+    /// Moves to a pending status, remembering the status to return to if the
+    /// request is rejected. A second request keeps the first one's.
+    fn enter_pending(&mut self, next: OrderStatus) -> PyResult<()> {
+        let prior = self.status;
+        self.transition(next)?;
+        if !prior.is_pending() {
+            self.prior_status = Some(prior);
+        }
+        Ok(())
+    }
+
+    fn transition(&mut self, next: OrderStatus) -> PyResult<()> {
+        if !self.status.can_transition_to(next) {
+            let err_msg = format!("Illegal transition for order {}: {} -> {}", self.order_id, self.status, next);
+            error!("{}", err_msg);
+            return Err(PyErr::new::<pyo3::exceptions::PyValueError, _>(err_msg));
+        }
+        info!("Order {} transitioned {} -> {}", self.order_id, self.status, next);
+        self.status = next;
+        Ok(())
+    }
+
+    fn apply_fill(&mut self, quantity: Decimal, price: Decimal) -> PyResult<()> {
+        if quantity <= Decimal::ZERO {
+            let err_msg = format!("Fill quantity must be positive, got {}", quantity);
+            error!("{}", err_msg);
+            return Err(PyErr::new::<pyo3::exceptions::PyValueError, _>(err_msg));
+        }
+        let filled = self.filled_quantity.saturating_add(quantity);
+        if filled > self.order.quantity {
+            let err_msg = format!("Fill of {} overfills order {} ({} of {} already filled)",
+                                  quantity, self.order_id, self.filled_quantity, self.order.quantity);
+            error!("{}", err_msg);
+            return Err(PyErr::new::<pyo3::exceptions::PyValueError, _>(err_msg));
+        }
+
+        let next = if filled >= self.order.quantity {
+            OrderStatus::Filled
+        } else if matches!(self.status, OrderStatus::PendingCancel | OrderStatus::PendingReplace) {
+            self.status
+        } else {
+            OrderStatus::PartiallyFilled
+        };
+        self.transition(next)?;
+
+        self.fill_notional = self.fill_notional.saturating_add(notional(quantity, price));
+        // The fill is positive, so only an overflow leaves no average
+        self.avg_fill_price = self.fill_notional.checked_div(filled).unwrap_or(Decimal::MAX);
+        self.filled_quantity = filled;
+        Ok(())
+    }
+
+    pub fn apply_report(&mut self, report: &ExecutionReport) -> PyResult<OrderStatus> {
+        match report.exec_type {
+            ExecType::PendingNew => self.transition(OrderStatus::PendingNew)?,
+            ExecType::Accepted => self.transition(OrderStatus::Accepted)?,
+            ExecType::Fill => self.apply_fill(report.last_quantity, report.last_price)?,
+            ExecType::Rejected => {
+                self.transition(OrderStatus::Rejected)?;
+                self.reject_reason = report.reason.clone();
+            },
+            ExecType::PendingCancel => self.enter_pending(OrderStatus::PendingCancel)?,
+            ExecType::Cancelled => self.transition(OrderStatus::Cancelled)?,
+            ExecType::Replaced => {
+                let (quantity, price, stop_price) = self.pending_amendment.take().ok_or_else(|| {
+                    PyErr::new::<pyo3::exceptions::PyValueError, _>(
+                        format!("Replace report for order {} with no pending replace", self.order_id))
+                })?;
+                self.order.amend(quantity, price, stop_price);
+                self.prior_status = None;
+                self.transition(self.working_status())?;
+            },
+            ExecType::CancelRejected => {
+                if !matches!(self.status, OrderStatus::PendingCancel | OrderStatus::PendingReplace) {
+                    let err_msg = format!("Cancel reject for order {} which is {}, not pending cancel or replace", self.order_id, self.status);
+                    error!("{}", err_msg);
+                    return Err(PyErr::new::<pyo3::exceptions::PyValueError, _>(err_msg));
+                }
+                // An order the broker hasn't acknowledged stays pending new until
+                // it trades; fills received while pending count either way
+                let restored = match self.prior_status.take() {
+                    Some(OrderStatus::PendingNew) if self.filled_quantity.is_zero() => OrderStatus::PendingNew,
+                    _ => self.working_status(),
+                };
+                self.pending_amendment = None;
+                self.transition(restored)?;
+                self.reject_reason = report.reason.clone();
+            },
+            ExecType::Expired => self.transition(OrderStatus::Expired)?,
+        }
+        Ok(self.status)
+    }
+}
+
+#[pymethods]
+impl OrderState {
+    pub fn leaves_quantity(&self) -> Decimal {
+        if self.status.is_terminal() {
+            Decimal::ZERO
+        } else {
+            self.order.quantity - self.filled_quantity
+        }
+    }
+
+    pub fn is_open(&self) -> bool {
+        !self.status.is_terminal()
+    }
+
+    pub fn __str__(&self) -> PyResult<String> {
+        Ok(format!("OrderState(order_id={}, status={}, filled_quantity={}, avg_fill_price={})",
+                   self.order_id, self.status, self.filled_quantity, self.avg_fill_price))
+    }
+}
+
+#[pyclass]
+#[derive(Clone, Default)]
+pub struct OrderStateTracker {
+    orders: HashMap<u64, OrderState>,
+}
+
+impl OrderStateTracker {
+    /// Fails exactly when `apply_report` would, without changing the order.
+    pub(crate) fn check_report(&self, order_id: u64, report: &ExecutionReport) -> PyResult<()> {
+        let mut state = self.orders.get(&order_id).cloned().ok_or_else(|| {
+            PyErr::new::<pyo3::exceptions::PyKeyError, _>(format!("Unknown order: {}", order_id))
+        })?;
+        state.apply_report(report).map(|_| ())
+    }
+
+    /// Expires every due order in order id order, returning the ids expired
+    /// and an error message for each order that couldn't be.
+    pub(crate) fn expire_due(&mut self, timestamp: f64, session_close: bool) -> (Vec<u64>, Vec<String>) {
+        let mut due: Vec<u64> = self.orders.values()
+            .filter(|s| s.is_open())
+            .filter(|s| match s.order.expire_time {
+                Some(expire_time) if s.order.time_in_force == TimeInForce::Gtd => expire_time <= timestamp,
+                _ => session_close && s.order.time_in_force.expires_at_session_close(),
+            })
+            .map(|s| s.order_id)
+            .collect();
+        due.sort_unstable();
+        let mut expired = Vec::new();
+        let mut failures = Vec::new();
+        for order_id in due {
+            let state = self.orders.get_mut(&order_id).unwrap();
+            match state.apply_report(&ExecutionReport::new(ExecType::Expired, Decimal::ZERO, Decimal::ZERO, None, Some(timestamp))) {
+                Ok(_) => expired.push(order_id),
+                Err(err) => {
+                    error!("Failed to expire order {}: {}", order_id, err);
+                    failures.push(format!("order {}: {}", order_id, err));
+                },
+            }
+        }
+        if !expired.is_empty() {
+            info!("Expired {} orders at {}", expired.len(), timestamp);
+        }
+        (expired, failures)
+    }
+}
+
+/// Returns the expired ids, or an error carrying them as `expired` when some
+/// due orders couldn't be expired.
+pub(crate) fn expire_result(py: Python, expired: Vec<u64>, failures: Vec<String>) -> PyResult<Vec<u64>> {
+    if failures.is_empty() {
+        return Ok(expired);
+    }
+    let err = PyErr::new::<pyo3::exceptions::PyValueError, _>(format!("Failed to expire orders: {}", failures.join("; ")));
+    err.value(py).setattr("expired", expired.into_py(py))?;
+    Err(err)
+}
+
+#[pymethods]
+impl OrderStateTracker {
+    #[new]
+    pub fn new() -> Self {
+        OrderStateTracker {
+            orders: HashMap::new(),
+        }
+    }
+
+    pub fn track(&mut self, order: Order) -> PyResult<()> {
+        if self.orders.contains_key(&order.order_id) {
+            let err_msg = format!("Order {} is already tracked", order.order_id);
+            error!("{}", err_msg);
+            return Err(PyErr::new::<pyo3::exceptions::PyValueError, _>(err_msg));
+        }
+        info!("Tracking order {}", order.order_id);
+        self.orders.insert(order.order_id, OrderState::new(order));
+        Ok(())
+    }
+
+    pub fn apply_report(&mut self, order_id: u64, report: &ExecutionReport) -> PyResult<OrderStatus> {
+        let state = self.orders.get_mut(&order_id).ok_or_else(|| {
+            PyErr::new::<pyo3::exceptions::PyKeyError, _>(format!("Unknown order: {}", order_id))
+        })?;
+        state.apply_report(report)
+    }
+
+    #[pyo3(signature = (order_id, quantity, price, stop_price=None))]
+    pub fn request_replace(&mut self, order_id: u64, quantity: Decimal, price: Decimal, stop_price: Option<Decimal>) -> PyResult<()> {
+        let state = self.orders.get_mut(&order_id).ok_or_else(|| {
+            PyErr::new::<pyo3::exceptions::PyKeyError, _>(format!("Unknown order: {}", order_id))
+        })?;
+        state.request_replace(quantity, price, stop_price)
+    }
+
+    pub fn get_state(&self, order_id: u64) -> Option<OrderState> {
+        self.orders.get(&order_id).cloned()
+    }
+
+    pub fn find_by_client_order_id(&self, client_order_id: &str) -> Option<OrderState> {
+        self.orders.values()
+            .find(|s| s.order.client_order_id.as_deref() == Some(client_order_id))
+            .cloned()
+    }
+
+    /// Expires open GTD orders whose expire time has passed and, when the
+    /// session has closed, every order that only lives for one session.
+    /// Orders that can't be expired don't stop the rest; the error lists
+    /// them and carries the ids that did expire as `expired`.
+    #[pyo3(signature = (timestamp, session_close=false))]
+    pub fn expire_orders(&mut self, py: Python, timestamp: f64, session_close: bool) -> PyResult<Vec<u64>> {
+        let (expired, failures) = self.expire_due(timestamp, session_close);
+        expire_result(py, expired, failures)
+    }
+
+    pub fn open_orders(&self) -> Vec<OrderState> {
+        self.orders.values()
+            .filter(|s| s.is_open())
+            .cloned()
+            .collect()
+    }
+
+    pub fn __len__(&self) -> usize {
+        self.orders.len()
+    }
+}
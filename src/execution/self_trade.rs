@@ -0,0 +1,178 @@
+use pyo3::prelude::*;
+use std::collections::HashMap;
+use std::sync::{Arc, RwLock};
+use crate::models::order::Order;
+use crate::models::numeric::Decimal;
+use crate::execution::order_state::{OrderState, OrderStatus};
+use crate::validation::error::ValidationError;
+
+/// What to do when an order would trade against a working order of the same account.
+#[pyclass]
+#[derive(Debug, Clone, Copy, Eq, PartialEq, Hash)]
+pub enum SelfTradePolicy {
+    /// Refuse the incoming order
+    RejectIncoming,
+    /// Cancel the resting orders it would trade with, then send it
+    CancelResting,
+    /// Take the crossing quantity off both the incoming and the resting orders
+    Net,
+}
+
+/// What the executor has to do to a resting order before sending the incoming one.
+#[derive(Debug, Clone, PartialEq)]
+pub enum SelfTradeAction {
+    Cancel(u64),
+    /// Amend the resting order down to the given total quantity
+    Reduce(u64, Decimal),
+}
+
+#[derive(Debug)]
+struct PreventionState {
+    default_policy: SelfTradePolicy,
+    policies: HashMap<String, SelfTradePolicy>,
+    // Working orders of every executor that uses this instance
+    working: HashMap<u64, OrderState>,
+}
+
+/// Per-account self-trade policies. Orders without an account share the
+/// default policy and are matched against each other. Clones share the
+/// policies and the working orders, so executors for different strategies
+/// of one account see each other's orders.
+///
+/// An executor can only cancel or net its own orders. An order that crosses
+/// another executor's order is rejected whatever the policy, so an account
+/// that relies on `CancelResting` or `Net` must send all of its orders
+/// through one executor.
+#[pyclass]
+#[derive(Debug, Clone)]
+pub struct SelfTradePrevention {
+    state: Arc<RwLock<PreventionState>>,
+}
+
+/// Whether `incoming` would trade against `resting` if both reached the
+/// same book. Stops are left out until they trigger, which the engine
+/// doesn't see.
+pub fn crosses(incoming: &Order, resting: &Order) -> bool {
+    if incoming.symbol != resting.symbol
+        || incoming.account != resting.account
+        || incoming.side.is_buy() == resting.side.is_buy()
+        || incoming.order_type.is_stop()
+        || resting.order_type.is_stop() {
+        return false;
+    }
+    let (buy, sell) = if incoming.side.is_buy() { (incoming, resting) } else { (resting, incoming) };
+    match (buy.limit_price, sell.limit_price) {
+        (Some(bid), Some(offer)) => bid >= offer,
+        // A market order trades with anything on the other side
+        _ => true,
+    }
+}
+
+impl SelfTradePrevention {
+    /// Records an order's latest state; orders that are done are dropped.
+    pub fn update(&self, state: &OrderState) {
+        let mut prevention = self.state.write().unwrap();
+        if state.is_open() {
+            prevention.working.insert(state.order_id, state.clone());
+        } else {
+            prevention.working.remove(&state.order_id);
+        }
+    }
+
+    /// Working orders `order` would trade against, oldest first.
+    fn resting(&self, order: &Order) -> Vec<OrderState> {
+        let prevention = self.state.read().unwrap();
+        let mut resting: Vec<OrderState> = prevention.working.values()
+            .filter(|s| s.order.order_id != order.order_id && s.is_open() && s.status != OrderStatus::PendingCancel)
+            .filter(|s| crosses(order, &s.order))
+            .cloned()
+            .collect();
+        resting.sort_by_key(|s| s.order.order_id);
+        resting
+    }
+
+    /// The oldest working order that `order` would trade against.
+    pub fn crossing(&self, order: &Order) -> Option<u64> {
+        self.resting(order).first().map(|s| s.order_id)
+    }
+
+    /// Matches `order` against the working orders and returns the quantity
+    /// left to send along with the actions to take on the resting orders.
+    /// Resting orders are taken oldest first. Only orders for which `owned`
+    /// holds can be cancelled or netted; crossing any other order, such as
+    /// one of another executor, rejects the incoming order.
+    pub fn resolve<F: Fn(u64) -> bool>(&self, order: &Order, owned: F) -> Result<(Decimal, Vec<SelfTradeAction>), ValidationError> {
+        let resting = self.resting(order);
+        if resting.is_empty() {
+            return Ok((order.quantity, Vec::new()));
+        }
+        let reject = |resting_order_id| Err(ValidationError::SelfTrade { symbol: order.symbol.clone(), resting_order_id });
+
+        let unowned = resting.iter().find(|s| !owned(s.order_id));
+        match (self.policy_for(order.account.as_deref()), unowned) {
+            (SelfTradePolicy::RejectIncoming, _) => reject(resting[0].order_id),
+            // Another executor's order can't be cancelled or amended from here
+            (_, Some(other)) => reject(other.order_id),
+            (SelfTradePolicy::CancelResting, None) => {
+                Ok((order.quantity, resting.iter().map(|s| SelfTradeAction::Cancel(s.order.order_id)).collect()))
+            },
+            (SelfTradePolicy::Net, None) => {
+                let mut remaining = order.quantity;
+                let mut actions = Vec::new();
+                for state in resting {
+                    if remaining.is_zero() {
+                        break;
+                    }
+                    let leaves = state.leaves_quantity();
+                    let netted = remaining.min(leaves);
+                    remaining -= netted;
+                    if netted == leaves {
+                        actions.push(SelfTradeAction::Cancel(state.order.order_id));
+                    } else {
+                        actions.push(SelfTradeAction::Reduce(state.order.order_id, state.order.quantity - netted));
+                    }
+                }
+                Ok((remaining, actions))
+            },
+        }
+    }
+}
+
+#[pymethods]
+impl SelfTradePrevention {
+    #[new]
+    #[pyo3(signature = (default_policy=SelfTradePolicy::RejectIncoming))]
+    pub fn new(default_policy: SelfTradePolicy) -> Self {
+        SelfTradePrevention {
+            state: Arc::new(RwLock::new(PreventionState {
+                default_policy,
+                policies: HashMap::new(),
+                working: HashMap::new(),
+            })),
+        }
+    }
+
+    #[getter]
+    pub fn default_policy(&self) -> SelfTradePolicy {
+        self.state.read().unwrap().default_policy
+    }
+
+    #[setter]
+    pub fn set_default_policy(&self, default_policy: SelfTradePolicy) {
+        self.state.write().unwrap().default_policy = default_policy;
+    }
+
+    pub fn set_policy(&self, account: String, policy: SelfTradePolicy) {
+        self.state.write().unwrap().policies.insert(account, policy);
+    }
+
+    pub fn remove_policy(&self, account: &str) -> Option<SelfTradePolicy> {
+        self.state.write().unwrap().policies.remove(account)
+    }
+
+    #[pyo3(signature = (account=None))]
+    pub fn policy_for(&self, account: Option<&str>) -> SelfTradePolicy {
+        let prevention = self.state.read().unwrap();
+        account.and_then(|a| prevention.policies.get(a)).copied().unwrap_or(prevention.default_policy)
+    }
+}
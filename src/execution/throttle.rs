@@ -0,0 +1,233 @@
+use pyo3::prelude::*;
+use std::collections::{HashMap, VecDeque};
+use std::sync::{Arc, RwLock};
+use log::warn;
+use crate::models::order::{Order, OrderType, Side, TimeInForce};
+use crate::models::numeric::Decimal;
+use crate::validation::error::ValidationError;
+
+/// What an order-rate limit is counted per.
+#[pyclass]
+#[derive(Debug, Clone, Copy, Eq, PartialEq, Hash)]
+pub enum ThrottleScope {
+    Strategy,
+    Account,
+    Symbol,
+}
+
+impl ThrottleScope {
+    const ALL: [ThrottleScope; 3] = [ThrottleScope::Strategy, ThrottleScope::Account, ThrottleScope::Symbol];
+
+    /// The bucket an order counts against; orders without a strategy or
+    /// account are not limited at that scope.
+    fn key(&self, order: &Order) -> Option<String> {
+        match self {
+            ThrottleScope::Strategy => order.strategy_id.clone(),
+            ThrottleScope::Account => order.account.clone(),
+            ThrottleScope::Symbol => Some(order.symbol.clone()),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+struct RateLimit {
+    per_second: f64,
+    burst: f64,
+}
+
+#[derive(Debug, Clone)]
+struct TokenBucket {
+    tokens: f64,
+    updated: f64,
+}
+
+impl TokenBucket {
+    fn refill(&mut self, limit: RateLimit, now: f64) {
+        // A clock that steps back must not mint tokens
+        let elapsed = (now - self.updated).max(0.0);
+        self.tokens = (self.tokens + elapsed * limit.per_second).min(limit.burst);
+        self.updated = self.updated.max(now);
+    }
+}
+
+/// Everything that makes two orders the same request.
+#[derive(Debug, Clone, PartialEq)]
+struct OrderFingerprint {
+    symbol: String,
+    side: Side,
+    quantity: Decimal,
+    order_type: OrderType,
+    price: Decimal,
+    stop_price: Option<Decimal>,
+    time_in_force: TimeInForce,
+    account: Option<String>,
+    strategy_id: Option<String>,
+}
+
+impl From<&Order> for OrderFingerprint {
+    fn from(order: &Order) -> Self {
+        OrderFingerprint {
+            symbol: order.symbol.clone(),
+            side: order.side,
+            quantity: order.quantity,
+            order_type: order.order_type,
+            price: order.price,
+            stop_price: order.stop_price,
+            time_in_force: order.time_in_force,
+            account: order.account.clone(),
+            strategy_id: order.strategy_id.clone(),
+        }
+    }
+}
+
+#[derive(Default)]
+struct ThrottleState {
+    limits: HashMap<ThrottleScope, RateLimit>,
+    buckets: HashMap<(ThrottleScope, String), TokenBucket>,
+    duplicate_window: Option<f64>,
+    recent: VecDeque<(f64, u64, OrderFingerprint)>,
+}
+
+impl ThrottleState {
+    /// Takes a token from every bucket the order counts against, or none of
+    /// them if any bucket is empty.
+    fn consume(&mut self, order: &Order, now: f64) -> Result<(), ValidationError> {
+        let mut keys = Vec::new();
+        for scope in ThrottleScope::ALL {
+            let (Some(limit), Some(key)) = (self.limits.get(&scope).copied(), scope.key(order)) else { continue };
+            let bucket = self.buckets.entry((scope, key.clone()))
+                .or_insert(TokenBucket { tokens: limit.burst, updated: now });
+            bucket.refill(limit, now);
+            if bucket.tokens < 1.0 {
+                return Err(ValidationError::Throttled { scope, key, per_second: limit.per_second });
+            }
+            keys.push((scope, key));
+        }
+        for key in keys {
+            if let Some(bucket) = self.buckets.get_mut(&key) {
+                bucket.tokens -= 1.0;
+            }
+        }
+        Ok(())
+    }
+
+    /// Hands back the token `consume` took from each of the order's buckets.
+    fn refund(&mut self, order: &Order) {
+        for scope in ThrottleScope::ALL {
+            let (Some(limit), Some(key)) = (self.limits.get(&scope).copied(), scope.key(order)) else { continue };
+            if let Some(bucket) = self.buckets.get_mut(&(scope, key)) {
+                bucket.tokens = (bucket.tokens + 1.0).min(limit.burst);
+            }
+        }
+    }
+
+    fn check_duplicate(&mut self, order: &Order, fingerprint: &OrderFingerprint, now: f64) -> Result<(), ValidationError> {
+        let Some(window) = self.duplicate_window else { return Ok(()) };
+        self.recent.retain(|(sent, _, _)| now - sent < window);
+        match self.recent.iter().find(|(_, _, f)| f == fingerprint) {
+            Some((_, previous_order_id, _)) => Err(ValidationError::DuplicateOrder {
+                symbol: order.symbol.clone(),
+                previous_order_id: *previous_order_id,
+                window,
+            }),
+            None => Ok(()),
+        }
+    }
+}
+
+/// Token-bucket order-rate limits per strategy, account and symbol, plus
+/// detection of identical orders sent within a short window. Clones share
+/// the same buckets, so one throttle can cover several executors.
+#[pyclass]
+#[derive(Clone, Default)]
+pub struct OrderThrottle {
+    state: Arc<RwLock<ThrottleState>>,
+}
+
+impl OrderThrottle {
+    /// Counts an amendment against the rate limits.
+    pub fn consume(&self, order: &Order, now: f64) -> Result<(), ValidationError> {
+        self.state.write().unwrap().consume(order, now).inspect_err(|err| warn!("Order {}: {}", order.order_id, err))
+    }
+
+    /// Admits a new order: refuses a repeat of an order sent within the
+    /// duplicate window, then applies the rate limits.
+    pub fn admit(&self, order: &Order, now: f64) -> Result<(), ValidationError> {
+        let fingerprint = OrderFingerprint::from(order);
+        let mut state = self.state.write().unwrap();
+        state.check_duplicate(order, &fingerprint, now)
+            .and_then(|_| state.consume(order, now))
+            .inspect_err(|err| warn!("Order {}: {}", order.order_id, err))?;
+        if state.duplicate_window.is_some() {
+            state.recent.push_back((now, order.order_id, fingerprint));
+        }
+        Ok(())
+    }
+
+    /// Undoes `admit` or `consume` for an order that was never sent, so a
+    /// retry is neither throttled nor taken for a duplicate.
+    pub fn release(&self, order: &Order) {
+        let mut state = self.state.write().unwrap();
+        state.refund(order);
+        state.recent.retain(|(_, order_id, _)| *order_id != order.order_id);
+    }
+}
+
+#[pymethods]
+impl OrderThrottle {
+    #[new]
+    #[pyo3(signature = (duplicate_window=None))]
+    pub fn new(duplicate_window: Option<f64>) -> Self {
+        let throttle = OrderThrottle::default();
+        throttle.state.write().unwrap().duplicate_window = duplicate_window;
+        throttle
+    }
+
+    /// Allows `per_second` orders per strategy, account or symbol, with
+    /// bursts of up to `burst` orders (by default one second's worth).
+    #[pyo3(signature = (scope, per_second, burst=None))]
+    pub fn set_rate(&self, scope: ThrottleScope, per_second: f64, burst: Option<f64>) -> PyResult<()> {
+        let burst = burst.unwrap_or(per_second.ceil());
+        if !per_second.is_finite() || !burst.is_finite() || per_second <= 0.0 || burst < 1.0 {
+            return Err(PyErr::new::<pyo3::exceptions::PyValueError, _>(
+                format!("Invalid rate for {:?}: {} per second with a burst of {}", scope, per_second, burst)));
+        }
+        let mut state = self.state.write().unwrap();
+        state.limits.insert(scope, RateLimit { per_second, burst });
+        state.buckets.retain(|(s, _), _| *s != scope);
+        Ok(())
+    }
+
+    pub fn remove_rate(&self, scope: ThrottleScope) {
+        let mut state = self.state.write().unwrap();
+        state.limits.remove(&scope);
+        state.buckets.retain(|(s, _), _| *s != scope);
+    }
+
+    /// Returns the `(per_second, burst)` limit for the scope.
+    pub fn get_rate(&self, scope: ThrottleScope) -> Option<(f64, f64)> {
+        self.state.read().unwrap().limits.get(&scope).map(|l| (l.per_second, l.burst))
+    }
+
+    /// Refuses orders identical to one sent less than `window` seconds ago;
+    /// `None` turns the check off.
+    pub fn set_duplicate_window(&self, window: Option<f64>) {
+        let mut state = self.state.write().unwrap();
+        state.duplicate_window = window;
+        if window.is_none() {
+            state.recent.clear();
+        }
+    }
+
+    #[getter]
+    pub fn duplicate_window(&self) -> Option<f64> {
+        self.state.read().unwrap().duplicate_window
+    }
+
+    /// Refills every bucket and forgets recently sent orders.
+    pub fn reset(&self) {
+        let mut state = self.state.write().unwrap();
+        state.buckets.clear();
+        state.recent.clear();
+    }
+}
@@ -5,29 +5,95 @@ mod validation;
 mod execution;
 mod risk;
 mod strategy;
+mod calendar;
+mod compliance;
 
 // Expose internally in production, publicly in tests
 use models::user::User;
-use models::order::{Order, OrderType};
-use validation::order_validator::{OrderValidator, ValidationRule};
+use models::order::{Order, OrderType, Side, TimeInForce};
+use models::instrument::{AssetClass, OptionRight, Instrument, InstrumentRegistry, TickTable};
+use models::market_data::{ReferencePrice, MarketData, MarketDataStore};
+use validation::order_validator::{OrderValidator, ValidationRule, RuleLayer, PriceCollar};
+use validation::report::{Severity, Violation, ValidationReport};
 use execution::executor::OrderExecutor;
+use execution::order_state::{OrderStatus, ExecType, ExecutionReport, OrderState, OrderStateTracker};
+use execution::contingent::{ContingencyType, OrderGroup};
+use execution::self_trade::{SelfTradePolicy, SelfTradePrevention};
+use execution::throttle::{OrderThrottle, ThrottleScope};
 use risk::risk_validator::RiskValidator;
+use risk::position_ledger::{Position, PositionLedger};
+use risk::pnl_engine::{CostBasis, PnlSummary, PnlEngine};
+use risk::risk_decision::{RiskLimit, LimitCheck, RiskDecision, RiskLimitError};
+use risk::limits::{LimitScope, ScopeLimits, LimitBook};
 use strategy::strategy_manager::StrategyManager;
 use strategy::strategy::{StrategyWrapper, StrategyStatus};
+use calendar::trading_calendar::{TradingSession, ExchangeCalendar, TradingCalendar};
+use calendar::clock::ClockHandle;
+use compliance::restricted_list::{RestrictionLevel, Restriction, RestrictedList};
+use compliance::locates::LocateInventory;
+use compliance::compliance_checker::ComplianceChecker;
 
 
 #[pymodule]
 fn quanta_engine(py: Python, m: &PyModule) -> PyResult<()> {
     m.add_class::<Order>()?;
     m.add_class::<OrderType>()?;
+    m.add_class::<Side>()?;
+    m.add_class::<TimeInForce>()?;
+    m.add_class::<AssetClass>()?;
+    m.add_class::<OptionRight>()?;
+    m.add_class::<Instrument>()?;
+    m.add_class::<InstrumentRegistry>()?;
+    m.add_class::<TickTable>()?;
+    m.add_class::<ReferencePrice>()?;
+    m.add_class::<MarketData>()?;
+    m.add_class::<MarketDataStore>()?;
     m.add_class::<OrderValidator>()?;
     m.add_class::<OrderExecutor>()?;
+    m.add_class::<OrderStatus>()?;
+    m.add_class::<ExecType>()?;
+    m.add_class::<ExecutionReport>()?;
+    m.add_class::<OrderState>()?;
+    m.add_class::<OrderStateTracker>()?;
+    m.add_class::<ContingencyType>()?;
+    m.add_class::<OrderGroup>()?;
+    m.add_class::<SelfTradePolicy>()?;
+    m.add_class::<SelfTradePrevention>()?;
+    m.add_class::<ThrottleScope>()?;
+    m.add_class::<OrderThrottle>()?;
     m.add_class::<RiskValidator>()?;
+    m.add_class::<Position>()?;
+    m.add_class::<PositionLedger>()?;
+    m.add_class::<CostBasis>()?;
+    m.add_class::<PnlSummary>()?;
+    m.add_class::<PnlEngine>()?;
+    m.add_class::<RiskLimit>()?;
+    m.add_class::<LimitCheck>()?;
+    m.add_class::<RiskDecision>()?;
+    m.add_class::<LimitScope>()?;
+    m.add_class::<ScopeLimits>()?;
+    m.add_class::<LimitBook>()?;
+    m.add("RiskLimitError", py.get_type::<RiskLimitError>())?;
     m.add_class::<StrategyManager>()?;
     m.add_class::<StrategyStatus>()?;
     m.add_class::<ValidationRule>()?;
+    m.add_class::<RuleLayer>()?;
+    m.add_class::<PriceCollar>()?;
+    m.add_class::<Severity>()?;
+    m.add_class::<Violation>()?;
+    m.add_class::<ValidationReport>()?;
     m.add_class::<User>()?;
     m.add_class::<StrategyWrapper>()?;
+    m.add_class::<TradingSession>()?;
+    m.add_class::<ExchangeCalendar>()?;
+    m.add_class::<TradingCalendar>()?;
+    m.add_class::<ClockHandle>()?;
+    m.add_class::<RestrictionLevel>()?;
+    m.add_class::<Restriction>()?;
+    m.add_class::<RestrictedList>()?;
+    m.add_class::<LocateInventory>()?;
+    m.add_class::<ComplianceChecker>()?;
+    validation::error::register(py, m)?;
 
     // Import Python's datetime module
     let datetime = py.import("datetime")?;
@@ -0,0 +1,227 @@
+use pyo3::prelude::*;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fmt;
+use std::sync::{Arc, RwLock};
+use log::{info, error};
+use crate::models::numeric::{notional, Decimal};
+
+#[pyclass]
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, Eq, PartialEq, Hash)]
+pub enum AssetClass {
+    Equity,
+    Future,
+    Option,
+    Fx,
+    Crypto,
+}
+
+impl fmt::Display for AssetClass {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{:?}", self)
+    }
+}
+
+#[pyclass]
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, Eq, PartialEq, Hash)]
+pub enum OptionRight {
+    Call,
+    Put,
+}
+
+/// Price-dependent tick sizes. Each tier applies from its lower price bound
+/// up to the next tier's bound.
+#[pyclass]
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct TickTable {
+    #[pyo3(get)]
+    pub tiers: Vec<(Decimal, Decimal)>,
+}
+
+#[pymethods]
+impl TickTable {
+    #[new]
+    pub fn new(tiers: Vec<(Decimal, Decimal)>) -> PyResult<Self> {
+        let ascending = tiers.windows(2).all(|pair| pair[0].0 < pair[1].0);
+        if tiers.is_empty() || !ascending || tiers.iter().any(|(_, tick)| *tick <= Decimal::ZERO) {
+            let err_msg = "Tick table tiers must be non-empty, ascending by price and have positive ticks";
+            error!("{}", err_msg);
+            return Err(PyErr::new::<pyo3::exceptions::PyValueError, _>(err_msg));
+        }
+        Ok(TickTable { tiers })
+    }
+
+    pub fn tick_for(&self, price: Decimal) -> Decimal {
+        self.tiers.iter()
+            .take_while(|(lower_bound, _)| *lower_bound <= price)
+            .last()
+            .unwrap_or(&self.tiers[0])
+            .1
+    }
+}
+
+#[pyclass]
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct Instrument {
+    #[pyo3(get)]
+    pub symbol: String,
+    #[pyo3(get)]
+    pub asset_class: AssetClass,
+    #[pyo3(get)]
+    pub exchange: String,
+    #[pyo3(get)]
+    pub currency: String,
+    #[pyo3(get)]
+    pub tick_size: Decimal,
+    /// Overrides `tick_size` when the tick depends on the price level
+    #[pyo3(get)]
+    pub tick_table: Option<TickTable>,
+    #[pyo3(get)]
+    pub lot_size: Decimal,
+    /// Contract multiplier; 1 for cash instruments
+    #[pyo3(get)]
+    pub multiplier: Decimal,
+    #[pyo3(get)]
+    pub expiry: Option<f64>,
+    #[pyo3(get)]
+    pub strike: Option<Decimal>,
+    #[pyo3(get)]
+    pub right: Option<OptionRight>,
+    /// Industry sector, for limits set on a group of instruments
+    #[pyo3(get)]
+    pub sector: Option<String>,
+}
+
+impl Instrument {
+    /// A plain equity for bare symbols. It has no tick or lot grid, since
+    /// none was configured: a zero increment accepts any price or quantity.
+    pub fn equity(symbol: String) -> Self {
+        Instrument {
+            symbol,
+            asset_class: AssetClass::Equity,
+            exchange: String::new(),
+            currency: "USD".to_string(),
+            tick_size: Decimal::ZERO,
+            tick_table: None,
+            lot_size: Decimal::ZERO,
+            multiplier: Decimal::ONE,
+            expiry: None,
+            strike: None,
+            right: None,
+            sector: None,
+        }
+    }
+}
+
+#[pymethods]
+impl Instrument {
+    #[new]
+    #[pyo3(signature = (symbol, asset_class, exchange, currency, tick_size=Decimal::new(1, 2), lot_size=Decimal::ONE,
+                        multiplier=Decimal::ONE, expiry=None, strike=None, right=None, tick_table=None, sector=None))]
+    #[allow(clippy::too_many_arguments)]
+    pub fn new(symbol: String, asset_class: AssetClass, exchange: String, currency: String, tick_size: Decimal, lot_size: Decimal,
+               multiplier: Decimal, expiry: Option<f64>, strike: Option<Decimal>, right: Option<OptionRight>,
+               tick_table: Option<TickTable>, sector: Option<String>) -> PyResult<Self> {
+        let err_msg = if tick_size <= Decimal::ZERO || lot_size <= Decimal::ZERO || multiplier <= Decimal::ZERO {
+            Some(format!("Tick size, lot size and multiplier of {} must be positive", symbol))
+        } else {
+            match asset_class {
+                AssetClass::Option if expiry.is_none() || strike.is_none() || right.is_none() =>
+                    Some(format!("Option {} requires an expiry, strike and right", symbol)),
+                AssetClass::Future if expiry.is_none() =>
+                    Some(format!("Future {} requires an expiry", symbol)),
+                AssetClass::Future if strike.is_some() || right.is_some() =>
+                    Some(format!("Strike and right are only valid for options, not future {}", symbol)),
+                AssetClass::Equity | AssetClass::Fx | AssetClass::Crypto if strike.is_some() || right.is_some() =>
+                    Some(format!("Strike and right are only valid for options, not {} {}", asset_class, symbol)),
+                _ => None,
+            }
+        };
+        if let Some(err_msg) = err_msg {
+            error!("{}", err_msg);
+            return Err(PyErr::new::<pyo3::exceptions::PyValueError, _>(err_msg));
+        }
+
+        Ok(Instrument {
+            symbol,
+            asset_class,
+            exchange,
+            currency,
+            tick_size,
+            tick_table,
+            lot_size,
+            multiplier,
+            expiry,
+            strike,
+            right,
+            sector,
+        })
+    }
+
+    /// Tick size that applies at `price`.
+    pub fn tick_for(&self, price: Decimal) -> Decimal {
+        match &self.tick_table {
+            Some(table) => table.tick_for(price),
+            None => self.tick_size,
+        }
+    }
+
+    /// Cash value of `quantity` at `price`, including the contract multiplier.
+    pub fn notional(&self, quantity: Decimal, price: Decimal) -> Decimal {
+        notional(notional(quantity, price), self.multiplier)
+    }
+
+    pub fn is_expired(&self, timestamp: f64) -> bool {
+        self.expiry.is_some_and(|expiry| expiry <= timestamp)
+    }
+
+    pub fn __str__(&self) -> PyResult<String> {
+        Ok(format!("Instrument(symbol={}, asset_class={}, exchange={}, currency={}, tick_size={}, lot_size={}, multiplier={})",
+                   self.symbol, self.asset_class, self.exchange, self.currency, self.tick_size, self.lot_size, self.multiplier))
+    }
+}
+
+/// Reference data for everything the engine trades. Clones share the same
+/// underlying table, so one registry can be handed to several validators and
+/// updated in place.
+#[pyclass]
+#[derive(Clone, Default)]
+pub struct InstrumentRegistry {
+    instruments: Arc<RwLock<HashMap<String, Instrument>>>,
+}
+
+#[pymethods]
+impl InstrumentRegistry {
+    #[new]
+    pub fn new() -> Self {
+        InstrumentRegistry {
+            instruments: Arc::new(RwLock::new(HashMap::new())),
+        }
+    }
+
+    pub fn add(&self, instrument: Instrument) -> PyResult<()> {
+        info!("Registered instrument: {}", instrument.symbol);
+        self.instruments.write().unwrap().insert(instrument.symbol.clone(), instrument);
+        Ok(())
+    }
+
+    pub fn remove(&self, symbol: &str) -> Option<Instrument> {
+        self.instruments.write().unwrap().remove(symbol)
+    }
+
+    pub fn get(&self, symbol: &str) -> Option<Instrument> {
+        self.instruments.read().unwrap().get(symbol).cloned()
+    }
+
+    pub fn contains(&self, symbol: &str) -> bool {
+        self.instruments.read().unwrap().contains_key(symbol)
+    }
+
+    pub fn symbols(&self) -> Vec<String> {
+        self.instruments.read().unwrap().keys().cloned().collect()
+    }
+
+    pub fn __len__(&self) -> usize {
+        self.instruments.read().unwrap().len()
+    }
+}
@@ -0,0 +1,95 @@
+use pyo3::prelude::*;
+use std::collections::HashMap;
+use std::sync::{Arc, RwLock};
+use log::info;
+use crate::models::numeric::Decimal;
+
+/// Which market price an order is measured against.
+#[pyclass]
+#[derive(Debug, Clone, Copy, Eq, PartialEq, Hash)]
+pub enum ReferencePrice {
+    Last,
+    Mid,
+    PreviousClose,
+}
+
+/// Latest market snapshot for one symbol. Fields the feed does not provide
+/// stay `None`.
+#[pyclass]
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct MarketData {
+    #[pyo3(get, set)]
+    pub last: Option<Decimal>,
+    #[pyo3(get, set)]
+    pub bid: Option<Decimal>,
+    #[pyo3(get, set)]
+    pub ask: Option<Decimal>,
+    #[pyo3(get, set)]
+    pub previous_close: Option<Decimal>,
+    /// Average daily volume in units of the instrument
+    #[pyo3(get, set)]
+    pub average_daily_volume: Option<Decimal>,
+}
+
+#[pymethods]
+impl MarketData {
+    #[new]
+    #[pyo3(signature = (last=None, bid=None, ask=None, previous_close=None, average_daily_volume=None))]
+    pub fn new(last: Option<Decimal>, bid: Option<Decimal>, ask: Option<Decimal>, previous_close: Option<Decimal>,
+               average_daily_volume: Option<Decimal>) -> Self {
+        MarketData { last, bid, ask, previous_close, average_daily_volume }
+    }
+
+    /// None when either side is missing, or when the quotes are too large
+    /// to average.
+    #[getter]
+    pub fn mid(&self) -> Option<Decimal> {
+        match (self.bid, self.ask) {
+            (Some(bid), Some(ask)) => bid.checked_add(ask).map(|sum| sum / Decimal::TWO),
+            _ => None,
+        }
+    }
+
+    pub fn reference(&self, reference: ReferencePrice) -> Option<Decimal> {
+        match reference {
+            ReferencePrice::Last => self.last,
+            ReferencePrice::Mid => self.mid(),
+            ReferencePrice::PreviousClose => self.previous_close,
+        }
+    }
+}
+
+/// Market snapshots by symbol. Like `InstrumentRegistry`, clones share the
+/// same table so a feed can update prices the validators already hold.
+#[pyclass]
+#[derive(Clone, Default)]
+pub struct MarketDataStore {
+    data: Arc<RwLock<HashMap<String, MarketData>>>,
+}
+
+#[pymethods]
+impl MarketDataStore {
+    #[new]
+    pub fn new() -> Self {
+        MarketDataStore {
+            data: Arc::new(RwLock::new(HashMap::new())),
+        }
+    }
+
+    pub fn update(&self, symbol: String, data: MarketData) {
+        info!("Updated market data for {}", symbol);
+        self.data.write().unwrap().insert(symbol, data);
+    }
+
+    pub fn get(&self, symbol: &str) -> Option<MarketData> {
+        self.data.read().unwrap().get(symbol).cloned()
+    }
+
+    pub fn remove(&self, symbol: &str) -> Option<MarketData> {
+        self.data.write().unwrap().remove(symbol)
+    }
+
+    pub fn __len__(&self) -> usize {
+        self.data.read().unwrap().len()
+    }
+}
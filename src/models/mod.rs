@@ -1,2 +1,5 @@
 pub mod order;
-pub mod user;
\ No newline at end of file
+pub mod user;
+pub mod numeric;
+pub mod instrument;
+pub mod market_data;
\ No newline at end of file
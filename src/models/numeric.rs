@@ -0,0 +1,39 @@
+use pyo3::prelude::*;
+use log::error;
+pub use rust_decimal::{Decimal, RoundingStrategy};
+
+/// Extracts a limit or bound, mapping Python's `float('inf')` to the widest
+/// representable decimal so that "no limit" settings keep working.
+pub fn extract_limit(value: &PyAny) -> PyResult<Decimal> {
+    if let Ok(float_value) = value.extract::<f64>() {
+        if float_value.is_infinite() {
+            return Ok(if float_value > 0.0 { Decimal::MAX } else { Decimal::MIN });
+        }
+    }
+    value.extract::<Decimal>()
+}
+
+/// Quantity times price, saturating instead of panicking on overflow.
+pub fn notional(quantity: Decimal, price: Decimal) -> Decimal {
+    quantity.checked_mul(price).unwrap_or(Decimal::MAX)
+}
+
+/// Whether `value` sits exactly on a grid of `increment`.
+pub fn is_multiple_of(value: Decimal, increment: Decimal) -> bool {
+    increment.is_zero() || (value % increment).is_zero()
+}
+
+/// Snaps `value` onto a grid of `increment`, rounding in the given direction.
+/// Fails when the grid is too fine for `value` to be counted in increments.
+pub fn round_to_increment(value: Decimal, increment: Decimal, strategy: RoundingStrategy) -> PyResult<Decimal> {
+    if increment.is_zero() {
+        return Ok(value);
+    }
+    value.checked_div(increment)
+        .and_then(|steps| steps.round_dp_with_strategy(0, strategy).checked_mul(increment))
+        .ok_or_else(|| {
+            let err_msg = format!("Cannot round {} to an increment of {}", value, increment);
+            error!("{}", err_msg);
+            PyErr::new::<pyo3::exceptions::PyValueError, _>(err_msg)
+        })
+}
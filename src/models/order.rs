@@ -2,15 +2,42 @@ use pyo3::prelude::*;
 use serde::{Deserialize, Serialize};
 use std::fmt;
 use std::collections::HashMap;
+use std::sync::atomic::{AtomicU64, Ordering};
 use pyo3::types::PyDateTime;
+use crate::models::numeric::Decimal;
+
+static NEXT_ORDER_ID: AtomicU64 = AtomicU64::new(1);
+
+/// Hands out engine-wide unique order ids.
+pub fn next_order_id() -> u64 {
+    NEXT_ORDER_ID.fetch_add(1, Ordering::Relaxed)
+}
 
 #[pyclass]
-#[derive(Debug, Serialize, Deserialize, Clone, Eq, PartialEq, Hash)]
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, Eq, PartialEq, Hash)]
 pub enum OrderType {
     Market,
     Limit,
     Stop,
     StopLimit,
+    TrailingStop,
+    TrailingStopLimit,
+}
+
+impl OrderType {
+    /// Order types that rest until a stop price is triggered.
+    pub fn is_stop(&self) -> bool {
+        matches!(self, OrderType::Stop | OrderType::StopLimit | OrderType::TrailingStop | OrderType::TrailingStopLimit)
+    }
+
+    pub fn is_trailing(&self) -> bool {
+        matches!(self, OrderType::TrailingStop | OrderType::TrailingStopLimit)
+    }
+
+    /// Order types whose `price` is a limit price.
+    pub fn has_limit_price(&self) -> bool {
+        matches!(self, OrderType::Limit | OrderType::StopLimit | OrderType::TrailingStopLimit)
+    }
 }
 
 impl fmt::Display for OrderType {
@@ -20,43 +47,199 @@ impl fmt::Display for OrderType {
             OrderType::Limit => write!(f, "Limit"),
             OrderType::Stop => write!(f, "Stop"),
             OrderType::StopLimit => write!(f, "StopLimit"),
+            OrderType::TrailingStop => write!(f, "TrailingStop"),
+            OrderType::TrailingStopLimit => write!(f, "TrailingStopLimit"),
+        }
+    }
+}
+
+#[pyclass]
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, Eq, PartialEq, Hash)]
+pub enum Side {
+    Buy,
+    Sell,
+    SellShort,
+    BuyToCover,
+}
+
+impl Side {
+    pub fn is_buy(&self) -> bool {
+        matches!(self, Side::Buy | Side::BuyToCover)
+    }
+
+    /// +1 for buy sides, -1 for sell sides.
+    pub fn sign(&self) -> Decimal {
+        if self.is_buy() { Decimal::ONE } else { Decimal::NEGATIVE_ONE }
+    }
+}
+
+impl fmt::Display for Side {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Side::Buy => write!(f, "Buy"),
+            Side::Sell => write!(f, "Sell"),
+            Side::SellShort => write!(f, "SellShort"),
+            Side::BuyToCover => write!(f, "BuyToCover"),
+        }
+    }
+}
+
+#[pyclass]
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, Eq, PartialEq, Hash)]
+pub enum TimeInForce {
+    Day,
+    Gtc,
+    Ioc,
+    Fok,
+    /// Good till date; the order carries an `expire_time`
+    Gtd,
+    /// At the opening auction
+    Opg,
+    /// At the closing auction
+    Cls,
+}
+
+impl TimeInForce {
+    /// Whether the order dies with the trading session it was entered in.
+    pub fn expires_at_session_close(&self) -> bool {
+        matches!(self, TimeInForce::Day | TimeInForce::Opg | TimeInForce::Cls)
+    }
+}
+
+impl fmt::Display for TimeInForce {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            TimeInForce::Day => write!(f, "DAY"),
+            TimeInForce::Gtc => write!(f, "GTC"),
+            TimeInForce::Ioc => write!(f, "IOC"),
+            TimeInForce::Fok => write!(f, "FOK"),
+            TimeInForce::Gtd => write!(f, "GTD"),
+            TimeInForce::Opg => write!(f, "OPG"),
+            TimeInForce::Cls => write!(f, "CLS"),
         }
     }
 }
 
+/// Accepts either a `datetime` or a float epoch timestamp.
+pub fn extract_timestamp(value: &PyAny) -> PyResult<f64> {
+    if value.is_instance_of::<PyDateTime>() {
+        value.call_method0("timestamp")?.extract::<f64>()
+    } else {
+        value.extract::<f64>()
+    }
+}
+
 #[pyclass]
 #[derive(Debug, Serialize, Deserialize, Clone)]
 pub struct Order {
+    #[pyo3(get)]
+    pub order_id: u64,
+    #[pyo3(get, set)]
+    pub client_order_id: Option<String>,
     #[pyo3(get, set)]
     pub symbol: String,
     #[pyo3(get, set)]
-    pub quantity: f64,
+    pub side: Side,
     #[pyo3(get, set)]
+    pub quantity: Decimal,
+    #[pyo3(get)]
     pub order_type: OrderType,
+    /// Limit price for limit-style orders, the trigger for plain stops and an
+    /// indicative price for market and trailing stop orders
+    #[pyo3(get)]
+    pub price: Decimal,
+    /// Set only for limit-style orders, and kept equal to `price`
+    #[pyo3(get)]
+    pub limit_price: Option<Decimal>,
+    #[pyo3(get, set)]
+    pub stop_price: Option<Decimal>,
     #[pyo3(get, set)]
-    pub price: f64,
+    pub trail_amount: Option<Decimal>,
+    #[pyo3(get, set)]
+    pub trail_percent: Option<Decimal>,
     #[pyo3(get, set)]
     pub timestamp: f64,
+    #[pyo3(get, set)]
+    pub time_in_force: TimeInForce,
+    #[pyo3(get, set)]
+    pub expire_time: Option<f64>,
+    /// Whether the order may trade in the pre- and post-market sessions
+    #[pyo3(get, set)]
+    pub extended_hours: bool,
+    /// Account the order is booked to
+    #[pyo3(get, set)]
+    pub account: Option<String>,
+    /// Strategy that generated the order
+    #[pyo3(get, set)]
+    pub strategy_id: Option<String>,
     #[pyo3(get)]
     pub additional_data: Option<HashMap<String, String>>,
 }
 
+impl Order {
+    /// Applies an amended quantity, working price and, when given, stop
+    /// price. A plain stop's trigger is its price, so the two stay in step.
+    pub fn amend(&mut self, quantity: Decimal, price: Decimal, stop_price: Option<Decimal>) {
+        self.quantity = quantity;
+        if self.order_type == OrderType::Stop {
+            self.set_price(stop_price.unwrap_or(price));
+        } else {
+            self.set_price(price);
+            if stop_price.is_some() {
+                self.stop_price = stop_price;
+            }
+        }
+    }
+}
+
 #[pymethods]
 impl Order {
     #[new]
-    pub fn new(symbol: String, quantity: f64, order_type: OrderType, price: f64, timestamp: &PyAny) -> PyResult<Self> {
-        let timestamp = if timestamp.is_instance_of::<PyDateTime>() {
-            timestamp.call_method0("timestamp")?.extract::<f64>()?
-        } else {
-            timestamp.extract::<f64>()?
+    #[pyo3(signature = (symbol, quantity, order_type, price, timestamp, side=None, client_order_id=None, time_in_force=TimeInForce::Day,
+                        expire_time=None, stop_price=None, trail_amount=None, trail_percent=None, account=None,
+                        extended_hours=false, strategy_id=None))]
+    #[allow(clippy::too_many_arguments)]
+    pub fn new(symbol: String, quantity: Decimal, order_type: OrderType, price: Decimal, timestamp: &PyAny, side: Option<Side>,
+               client_order_id: Option<String>, time_in_force: TimeInForce, expire_time: Option<&PyAny>,
+               stop_price: Option<Decimal>, trail_amount: Option<Decimal>, trail_percent: Option<Decimal>,
+               account: Option<String>, extended_hours: bool, strategy_id: Option<String>) -> PyResult<Self> {
+        // Without an explicit side the sign of the quantity gives the direction
+        let side = match side {
+            Some(side) if quantity.is_sign_negative() && side.is_buy() => {
+                return Err(PyErr::new::<pyo3::exceptions::PyValueError, _>(
+                    format!("Negative quantity {} conflicts with side {}", quantity, side)));
+            },
+            Some(side) => side,
+            None if quantity.is_sign_negative() => Side::Sell,
+            None => Side::Buy,
+        };
+
+        let timestamp = extract_timestamp(timestamp)?;
+        let expire_time = expire_time.map(extract_timestamp).transpose()?;
+        // A plain stop has always carried its trigger in `price`
+        let stop_price = match order_type {
+            OrderType::Stop => stop_price.or(Some(price)),
+            _ => stop_price,
         };
         
         Ok(Order {
+            order_id: next_order_id(),
+            client_order_id,
             symbol,
-            quantity,
+            side,
+            quantity: quantity.abs(),
             order_type,
             price,
+            limit_price: order_type.has_limit_price().then_some(price),
+            stop_price,
+            trail_amount,
+            trail_percent,
             timestamp,
+            time_in_force,
+            expire_time,
+            extended_hours,
+            account,
+            strategy_id,
             additional_data: None,
         })
     }
@@ -65,9 +248,42 @@ impl Order {
         self.additional_data = Some(data);
     }
 
+    #[setter]
+    pub fn set_order_type(&mut self, order_type: OrderType) {
+        self.order_type = order_type;
+        self.limit_price = order_type.has_limit_price().then_some(self.price);
+    }
+
+    #[setter]
+    pub fn set_price(&mut self, price: Decimal) {
+        self.price = price;
+        if self.order_type.has_limit_price() {
+            self.limit_price = Some(price);
+        }
+        if self.order_type == OrderType::Stop {
+            self.stop_price = Some(price);
+        }
+    }
+
+    /// Sets the limit of a limit-style order, which is also its `price`.
+    #[setter]
+    pub fn set_limit_price(&mut self, limit_price: Decimal) -> PyResult<()> {
+        if !self.order_type.has_limit_price() {
+            return Err(PyErr::new::<pyo3::exceptions::PyValueError, _>(
+                format!("{} order {} has no limit price", self.order_type, self.order_id)));
+        }
+        self.set_price(limit_price);
+        Ok(())
+    }
+
+    /// Quantity with the direction applied: positive for buys, negative for sells.
+    pub fn signed_quantity(&self) -> Decimal {
+        self.side.sign() * self.quantity
+    }
+
     pub fn __str__(&self) -> PyResult<String> {
-        Ok(format!("Order(symbol={}, quantity={}, order_type={:?}, price={}, timestamp={})",
-                   self.symbol, self.quantity, self.order_type, self.price, self.timestamp))
+        Ok(format!("Order(order_id={}, symbol={}, side={}, quantity={}, order_type={:?}, price={}, stop_price={:?}, time_in_force={}, timestamp={})",
+                   self.order_id, self.symbol, self.side, self.quantity, self.order_type, self.price, self.stop_price, self.time_in_force, self.timestamp))
     }
 
     pub fn set_timestamp_py(&mut self, timestamp: &PyDateTime) -> PyResult<()> {
@@ -0,0 +1,69 @@
+use std::collections::HashMap;
+use log::warn;
+use crate::models::numeric::{notional, Decimal};
+use crate::models::instrument::InstrumentRegistry;
+
+/// Market value of a portfolio's long and short holdings, with the number
+/// of open positions and the gross value held in each sector.
+#[derive(Debug, Default)]
+pub struct Exposure {
+    pub long: Decimal,
+    pub short: Decimal,
+    pub open_positions: usize,
+    sectors: HashMap<String, Decimal>,
+}
+
+impl Exposure {
+    /// Values net holdings given as `(quantity, price)` by symbol, using the
+    /// instrument's multiplier when known. A holding with no price can't be
+    /// valued and counts as the largest possible value, so that exposure
+    /// limits fail closed.
+    pub fn of(holdings: &HashMap<String, (Decimal, Decimal)>, instruments: Option<&InstrumentRegistry>) -> Self {
+        let mut exposure = Exposure::default();
+        for (symbol, (quantity, price)) in holdings {
+            if quantity.is_zero() {
+                continue;
+            }
+            let instrument = instruments.and_then(|registry| registry.get(symbol));
+            let value = if price.is_zero() {
+                warn!("No price to value {} {} at", quantity, symbol);
+                Decimal::MAX
+            } else {
+                match &instrument {
+                    Some(instrument) => instrument.notional(quantity.abs(), *price),
+                    None => notional(quantity.abs(), *price),
+                }
+            };
+            if quantity.is_sign_positive() {
+                exposure.long = exposure.long.saturating_add(value);
+            } else {
+                exposure.short = exposure.short.saturating_add(value);
+            }
+            exposure.open_positions += 1;
+            if let Some(sector) = instrument.and_then(|i| i.sector) {
+                let sector_value = exposure.sectors.entry(sector).or_default();
+                *sector_value = sector_value.saturating_add(value);
+            }
+        }
+        exposure
+    }
+
+    pub fn gross(&self) -> Decimal {
+        self.long.saturating_add(self.short)
+    }
+
+    pub fn net(&self) -> Decimal {
+        self.long.saturating_sub(self.short)
+    }
+
+    /// Gross value held in the sector as a percentage of `equity`.
+    pub fn concentration(&self, sector: &str, equity: Decimal) -> Decimal {
+        let value = self.sectors.get(sector).copied().unwrap_or_default();
+        value.checked_div(equity).and_then(|share| share.checked_mul(Decimal::ONE_HUNDRED)).unwrap_or(Decimal::MAX)
+    }
+
+    /// The sector with the largest gross value, if any is held.
+    pub fn largest_sector(&self) -> Option<&str> {
+        self.sectors.iter().max_by_key(|(_, value)| **value).map(|(sector, _)| sector.as_str())
+    }
+}
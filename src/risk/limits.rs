@@ -0,0 +1,246 @@
+use pyo3::prelude::*;
+use std::collections::HashMap;
+use std::sync::{Arc, RwLock};
+use log::{info, error};
+use crate::models::order::Order;
+use crate::models::numeric::Decimal;
+use crate::models::instrument::InstrumentRegistry;
+use crate::risk::exposure::Exposure;
+use crate::risk::risk_decision::{LimitCheck, RiskLimit};
+
+/// Levels of the limit hierarchy, widest first. Sector and asset-class
+/// scopes group instruments by their reference data.
+#[pyclass]
+#[derive(Debug, Clone, Copy, Eq, PartialEq, Hash)]
+pub enum LimitScope {
+    Firm,
+    Account,
+    Strategy,
+    Instrument,
+    Sector,
+    AssetClass,
+}
+
+impl LimitScope {
+    const ALL: [LimitScope; 6] = [LimitScope::Firm, LimitScope::Account, LimitScope::Strategy,
+                                  LimitScope::Instrument, LimitScope::Sector, LimitScope::AssetClass];
+
+    /// Key of the scope of this kind that holdings of `symbol` by `account`
+    /// and `strategy_id` fall under, if any. The firm's key is empty.
+    pub fn key_for(&self, account: Option<&str>, strategy_id: Option<&str>, symbol: &str,
+                   instruments: Option<&InstrumentRegistry>) -> Option<String> {
+        match self {
+            LimitScope::Firm => Some(String::new()),
+            LimitScope::Account => account.map(str::to_string),
+            LimitScope::Strategy => strategy_id.map(str::to_string),
+            LimitScope::Instrument => Some(symbol.to_string()),
+            LimitScope::Sector => instruments.and_then(|r| r.get(symbol)).and_then(|i| i.sector),
+            LimitScope::AssetClass => instruments.and_then(|r| r.get(symbol)).map(|i| i.asset_class.to_string()),
+        }
+    }
+
+    pub fn contains(&self, key: &str, account: Option<&str>, strategy_id: Option<&str>, symbol: &str,
+                    instruments: Option<&InstrumentRegistry>) -> bool {
+        self.key_for(account, strategy_id, symbol, instruments).is_some_and(|k| k == key)
+    }
+}
+
+/// Limits of one scope; unset limits are not checked. Exposure limits apply
+/// to everything the scope holds once the order fills.
+#[pyclass]
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct ScopeLimits {
+    #[pyo3(get, set)]
+    pub max_order_quantity: Option<Decimal>,
+    #[pyo3(get, set)]
+    pub max_order_value: Option<Decimal>,
+    /// Net position the scope may hold in any one instrument
+    #[pyo3(get, set)]
+    pub max_position: Option<Decimal>,
+    #[pyo3(get, set)]
+    pub max_daily_loss: Option<Decimal>,
+    #[pyo3(get, set)]
+    pub max_gross_exposure: Option<Decimal>,
+    /// Limit on long less short exposure in either direction
+    #[pyo3(get, set)]
+    pub max_net_exposure: Option<Decimal>,
+    #[pyo3(get, set)]
+    pub max_long_exposure: Option<Decimal>,
+    #[pyo3(get, set)]
+    pub max_short_exposure: Option<Decimal>,
+    /// Percentage of `equity` any one sector may make up
+    #[pyo3(get)]
+    pub max_sector_concentration: Option<Decimal>,
+    #[pyo3(get, set)]
+    pub max_open_positions: Option<usize>,
+    /// Equity of the scope that sector concentration is measured against
+    #[pyo3(get)]
+    pub equity: Option<Decimal>,
+}
+
+fn check_equity(max_sector_concentration: Option<Decimal>, equity: Option<Decimal>) -> PyResult<()> {
+    if equity.is_some_and(|equity| equity <= Decimal::ZERO) || (max_sector_concentration.is_some() && equity.is_none()) {
+        let err_msg = "Equity must be positive, and a sector concentration limit needs it";
+        error!("{}", err_msg);
+        return Err(PyErr::new::<pyo3::exceptions::PyValueError, _>(err_msg));
+    }
+    Ok(())
+}
+
+impl ScopeLimits {
+    pub fn has_exposure_limits(&self) -> bool {
+        self.max_gross_exposure.is_some() || self.max_net_exposure.is_some() || self.max_long_exposure.is_some()
+            || self.max_short_exposure.is_some() || self.max_sector_concentration.is_some() || self.max_open_positions.is_some()
+    }
+
+    /// Checks the exposure limits against a portfolio, with concentration
+    /// checked for `sector`.
+    pub fn exposure_checks(&self, exposure: &Exposure, sector: Option<&str>) -> Vec<LimitCheck> {
+        let mut checks = Vec::new();
+        if let Some(max_gross_exposure) = self.max_gross_exposure {
+            checks.push(LimitCheck::new(RiskLimit::GrossExposure, max_gross_exposure, exposure.gross()));
+        }
+        if let Some(max_net_exposure) = self.max_net_exposure {
+            checks.push(LimitCheck::new(RiskLimit::NetExposure, max_net_exposure, exposure.net().abs()));
+        }
+        if let Some(max_long_exposure) = self.max_long_exposure {
+            checks.push(LimitCheck::new(RiskLimit::LongExposure, max_long_exposure, exposure.long));
+        }
+        if let Some(max_short_exposure) = self.max_short_exposure {
+            checks.push(LimitCheck::new(RiskLimit::ShortExposure, max_short_exposure, exposure.short));
+        }
+        if let (Some(max_sector_concentration), Some(equity), Some(sector)) = (self.max_sector_concentration, self.equity, sector) {
+            checks.push(LimitCheck::new(RiskLimit::SectorConcentration, max_sector_concentration, exposure.concentration(sector, equity)));
+        }
+        if let Some(max_open_positions) = self.max_open_positions {
+            checks.push(LimitCheck::new(RiskLimit::OpenPositions, max_open_positions.into(), exposure.open_positions.into()));
+        }
+        checks
+    }
+}
+
+#[pymethods]
+impl ScopeLimits {
+    #[new]
+    #[pyo3(signature = (max_order_quantity=None, max_order_value=None, max_position=None, max_daily_loss=None,
+                        max_gross_exposure=None, max_net_exposure=None, max_long_exposure=None, max_short_exposure=None,
+                        max_sector_concentration=None, max_open_positions=None, equity=None))]
+    #[allow(clippy::too_many_arguments)]
+    pub fn new(max_order_quantity: Option<Decimal>, max_order_value: Option<Decimal>, max_position: Option<Decimal>,
+               max_daily_loss: Option<Decimal>, max_gross_exposure: Option<Decimal>, max_net_exposure: Option<Decimal>,
+               max_long_exposure: Option<Decimal>, max_short_exposure: Option<Decimal>, max_sector_concentration: Option<Decimal>,
+               max_open_positions: Option<usize>, equity: Option<Decimal>) -> PyResult<Self> {
+        check_equity(max_sector_concentration, equity)?;
+        Ok(ScopeLimits {
+            max_order_quantity,
+            max_order_value,
+            max_position,
+            max_daily_loss,
+            max_gross_exposure,
+            max_net_exposure,
+            max_long_exposure,
+            max_short_exposure,
+            max_sector_concentration,
+            max_open_positions,
+            equity,
+        })
+    }
+
+    #[setter]
+    pub fn set_max_sector_concentration(&mut self, max_sector_concentration: Option<Decimal>) -> PyResult<()> {
+        check_equity(max_sector_concentration, self.equity)?;
+        self.max_sector_concentration = max_sector_concentration;
+        Ok(())
+    }
+
+    #[setter]
+    pub fn set_equity(&mut self, equity: Option<Decimal>) -> PyResult<()> {
+        check_equity(self.max_sector_concentration, equity)?;
+        self.equity = equity;
+        Ok(())
+    }
+
+    pub fn __str__(&self) -> String {
+        format!("ScopeLimits(max_order_quantity={:?}, max_order_value={:?}, max_position={:?}, max_daily_loss={:?}, \
+                 max_gross_exposure={:?}, max_net_exposure={:?}, max_long_exposure={:?}, max_short_exposure={:?}, \
+                 max_sector_concentration={:?}, max_open_positions={:?}, equity={:?})",
+                self.max_order_quantity, self.max_order_value, self.max_position, self.max_daily_loss,
+                self.max_gross_exposure, self.max_net_exposure, self.max_long_exposure, self.max_short_exposure,
+                self.max_sector_concentration, self.max_open_positions, self.equity)
+    }
+}
+
+/// Limits by scope. Clones share the same table, so limits can be changed
+/// from Python while validators that hold the book keep running.
+#[pyclass]
+#[derive(Clone, Default)]
+pub struct LimitBook {
+    limits: Arc<RwLock<HashMap<(LimitScope, String), ScopeLimits>>>,
+}
+
+fn scope_key(scope: LimitScope, key: Option<String>) -> PyResult<String> {
+    match (scope, key) {
+        (LimitScope::Firm, _) => Ok(String::new()),
+        (_, Some(key)) => Ok(key),
+        (_, None) => {
+            let err_msg = format!("{:?} limits need a key", scope);
+            error!("{}", err_msg);
+            Err(PyErr::new::<pyo3::exceptions::PyValueError, _>(err_msg))
+        },
+    }
+}
+
+impl LimitBook {
+    /// Every scope with limits that the order belongs to, widest first.
+    pub fn scopes_for(&self, order: &Order, instruments: Option<&InstrumentRegistry>) -> Vec<(LimitScope, String, ScopeLimits)> {
+        let limits = self.limits.read().unwrap();
+        LimitScope::ALL.iter()
+            .filter_map(|scope| {
+                let key = scope.key_for(order.account.as_deref(), order.strategy_id.as_deref(), &order.symbol, instruments)?;
+                let scope_limits = limits.get(&(*scope, key.clone()))?;
+                Some((*scope, key, scope_limits.clone()))
+            })
+            .collect()
+    }
+}
+
+#[pymethods]
+impl LimitBook {
+    #[new]
+    pub fn new() -> Self {
+        LimitBook::default()
+    }
+
+    /// Sets the limits of a scope. `key` names the account, strategy,
+    /// symbol, sector or asset class, and is not needed for the firm.
+    #[pyo3(signature = (scope, limits, key=None))]
+    pub fn set(&self, scope: LimitScope, limits: ScopeLimits, key: Option<String>) -> PyResult<()> {
+        let key = scope_key(scope, key)?;
+        info!("Set {:?} limits for '{}': {}", scope, key, limits.__str__());
+        self.limits.write().unwrap().insert((scope, key), limits);
+        Ok(())
+    }
+
+    #[pyo3(signature = (scope, key=None))]
+    pub fn get(&self, scope: LimitScope, key: Option<String>) -> PyResult<Option<ScopeLimits>> {
+        let key = scope_key(scope, key)?;
+        Ok(self.limits.read().unwrap().get(&(scope, key)).cloned())
+    }
+
+    #[pyo3(signature = (scope, key=None))]
+    pub fn remove(&self, scope: LimitScope, key: Option<String>) -> PyResult<Option<ScopeLimits>> {
+        let key = scope_key(scope, key)?;
+        Ok(self.limits.write().unwrap().remove(&(scope, key)))
+    }
+
+    /// Scopes that have limits, as `(scope, key)` pairs; the firm's key is `None`.
+    pub fn scopes(&self) -> Vec<(LimitScope, Option<String>)> {
+        self.limits.read().unwrap().keys()
+            .map(|(scope, key)| (*scope, if *scope == LimitScope::Firm { None } else { Some(key.clone()) }))
+            .collect()
+    }
+
+    pub fn __len__(&self) -> usize {
+        self.limits.read().unwrap().len()
+    }
+}
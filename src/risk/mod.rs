@@ -1 +1,6 @@
-pub mod risk_validator;
\ No newline at end of file
+pub mod risk_validator;
+pub mod position_ledger;
+pub mod pnl_engine;
+pub mod risk_decision;
+pub mod limits;
+pub mod exposure;
\ No newline at end of file
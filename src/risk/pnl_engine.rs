@@ -0,0 +1,374 @@
+use pyo3::prelude::*;
+use chrono::{DateTime, Duration, NaiveTime, TimeZone};
+use chrono_tz::Tz;
+use std::collections::{HashMap, VecDeque};
+use std::sync::{Arc, RwLock};
+use log::{info, error};
+use crate::models::order::Order;
+use crate::models::numeric::Decimal;
+use crate::models::instrument::InstrumentRegistry;
+use crate::models::market_data::{MarketDataStore, ReferencePrice};
+use crate::calendar::clock::ClockHandle;
+use crate::calendar::trading_calendar::parse_time;
+
+/// Which open lots a closing fill is matched against.
+#[pyclass]
+#[derive(Debug, Clone, Copy, Eq, PartialEq, Hash)]
+pub enum CostBasis {
+    Fifo,
+    Lifo,
+    /// A single lot at the average cost of the open quantity
+    AverageCost,
+}
+
+#[derive(Debug, Clone, Eq, PartialEq, Hash)]
+struct BookKey {
+    account: Option<String>,
+    strategy_id: Option<String>,
+    symbol: String,
+}
+
+#[derive(Debug, Clone)]
+struct Lot {
+    /// Positive for a long lot, negative for a short one
+    quantity: Decimal,
+    price: Decimal,
+}
+
+/// Open lots and realized PnL of one strategy's holding in one instrument.
+#[derive(Debug, Clone, Default)]
+struct Book {
+    lots: VecDeque<Lot>,
+    realized: Decimal,
+    daily_realized: Decimal,
+    // Unrealized PnL when the current session started
+    start_unrealized: Decimal,
+}
+
+impl Book {
+    /// Books a signed fill and returns the PnL it realized. Amounts too large
+    /// to represent saturate rather than panic.
+    fn apply_fill(&mut self, cost_basis: CostBasis, mut quantity: Decimal, price: Decimal, multiplier: Decimal) -> Decimal {
+        let mut realized = Decimal::ZERO;
+        while !quantity.is_zero() {
+            let lot = match cost_basis {
+                CostBasis::Lifo => self.lots.back_mut(),
+                CostBasis::Fifo | CostBasis::AverageCost => self.lots.front_mut(),
+            };
+            match lot {
+                // The fill closes out some or all of this lot
+                Some(lot) if lot.quantity.is_sign_positive() != quantity.is_sign_positive() => {
+                    let lot_sign = if lot.quantity.is_sign_positive() { Decimal::ONE } else { Decimal::NEGATIVE_ONE };
+                    let closed = lot_sign * quantity.abs().min(lot.quantity.abs());
+                    let pnl = price.saturating_sub(lot.price).saturating_mul(closed).saturating_mul(multiplier);
+                    realized = realized.saturating_add(pnl);
+                    lot.quantity -= closed;
+                    quantity += closed;
+                    if lot.quantity.is_zero() {
+                        match cost_basis {
+                            CostBasis::Lifo => self.lots.pop_back(),
+                            CostBasis::Fifo | CostBasis::AverageCost => self.lots.pop_front(),
+                        };
+                    }
+                },
+                Some(lot) if cost_basis == CostBasis::AverageCost => {
+                    // Both quantities have the same sign, so the total is never zero
+                    let total = lot.quantity.saturating_add(quantity);
+                    let cost = lot.quantity.saturating_mul(lot.price).saturating_add(quantity.saturating_mul(price));
+                    lot.price = cost.checked_div(total).unwrap_or(Decimal::MAX);
+                    lot.quantity = total;
+                    quantity = Decimal::ZERO;
+                },
+                _ => {
+                    self.lots.push_back(Lot { quantity, price });
+                    quantity = Decimal::ZERO;
+                },
+            }
+        }
+        self.realized = self.realized.saturating_add(realized);
+        self.daily_realized = self.daily_realized.saturating_add(realized);
+        realized
+    }
+
+    fn unrealized(&self, mark: Option<Decimal>, multiplier: Decimal) -> Decimal {
+        match mark {
+            Some(mark) => self.lots.iter()
+                .map(|lot| mark.saturating_sub(lot.price).saturating_mul(lot.quantity).saturating_mul(multiplier))
+                .fold(Decimal::ZERO, Decimal::saturating_add),
+            None => Decimal::ZERO,
+        }
+    }
+}
+
+/// Realized, unrealized and current-session PnL of a set of books.
+#[pyclass]
+#[derive(Debug, Clone, Default)]
+pub struct PnlSummary {
+    #[pyo3(get)]
+    pub realized: Decimal,
+    #[pyo3(get)]
+    pub unrealized: Decimal,
+    /// Realized plus change in unrealized since the session boundary
+    #[pyo3(get)]
+    pub daily: Decimal,
+}
+
+#[pymethods]
+impl PnlSummary {
+    #[getter]
+    pub fn total(&self) -> Decimal {
+        self.realized.saturating_add(self.unrealized)
+    }
+
+    pub fn __str__(&self) -> String {
+        format!("PnlSummary(realized={}, unrealized={}, daily={})", self.realized, self.unrealized, self.daily)
+    }
+}
+
+struct PnlState {
+    cost_basis: CostBasis,
+    books: HashMap<BookKey, Book>,
+    marks: HashMap<String, Decimal>,
+    market_data: Option<MarketDataStore>,
+    instruments: Option<InstrumentRegistry>,
+    clock: ClockHandle,
+    session_boundary: NaiveTime,
+    timezone: Tz,
+    session_start: Option<f64>,
+}
+
+impl PnlState {
+    fn multiplier(&self, symbol: &str) -> Decimal {
+        self.instruments.as_ref()
+            .and_then(|registry| registry.get(symbol))
+            .map(|instrument| instrument.multiplier)
+            .unwrap_or(Decimal::ONE)
+    }
+
+    /// Explicit marks win over the last price in the market data.
+    fn mark(&self, symbol: &str) -> Option<Decimal> {
+        self.marks.get(symbol).copied().or_else(|| {
+            self.market_data.as_ref()
+                .and_then(|store| store.get(symbol))
+                .and_then(|data| data.reference(ReferencePrice::Last))
+        })
+    }
+
+    fn unrealized(&self, key: &BookKey, book: &Book) -> Decimal {
+        book.unrealized(self.mark(&key.symbol), self.multiplier(&key.symbol))
+    }
+
+    /// Most recent session boundary at or before `timestamp`.
+    fn boundary_before(&self, timestamp: f64) -> f64 {
+        if !timestamp.is_finite() {
+            return timestamp;
+        }
+        let utc = DateTime::from_timestamp(timestamp.floor() as i64, 0).unwrap_or_default();
+        let mut date = utc.with_timezone(&self.timezone).date_naive();
+        loop {
+            let local = date.and_time(self.session_boundary);
+            // A boundary that falls in a DST gap moves to after the gap
+            let boundary = self.timezone.from_local_datetime(&local).earliest()
+                .or_else(|| self.timezone.from_local_datetime(&(local + Duration::hours(1))).earliest())
+                .map(|boundary| boundary.timestamp() as f64);
+            if let Some(boundary) = boundary.filter(|b| *b <= timestamp) {
+                return boundary;
+            }
+            let Some(previous) = date.pred_opt() else { return timestamp };
+            date = previous;
+        }
+    }
+
+    /// Starts a new session once `timestamp` passes the next boundary:
+    /// daily realized PnL goes back to zero and open positions are measured
+    /// from their unrealized PnL at the current marks.
+    fn roll(&mut self, timestamp: f64) {
+        let boundary = self.boundary_before(timestamp);
+        if self.session_start.is_some_and(|start| boundary <= start) {
+            return;
+        }
+        if self.session_start.is_some() {
+            info!("Starting new PnL session at {}", boundary);
+            let start_unrealized: Vec<(BookKey, Decimal)> = self.books.iter()
+                .map(|(key, book)| (key.clone(), self.unrealized(key, book)))
+                .collect();
+            for (key, unrealized) in start_unrealized {
+                if let Some(book) = self.books.get_mut(&key) {
+                    book.daily_realized = Decimal::ZERO;
+                    book.start_unrealized = unrealized;
+                }
+            }
+        }
+        self.session_start = Some(boundary);
+    }
+
+    fn summary<F: Fn(&BookKey) -> bool>(&mut self, filter: F) -> PnlSummary {
+        let now = self.clock.now();
+        self.roll(now);
+        let mut summary = PnlSummary::default();
+        for (key, book) in self.books.iter().filter(|(key, _)| filter(key)) {
+            let unrealized = self.unrealized(key, book);
+            summary.realized = summary.realized.saturating_add(book.realized);
+            summary.unrealized = summary.unrealized.saturating_add(unrealized);
+            let daily = book.daily_realized.saturating_add(unrealized).saturating_sub(book.start_unrealized);
+            summary.daily = summary.daily.saturating_add(daily);
+        }
+        summary
+    }
+}
+
+/// Realized PnL per fill and unrealized PnL from mark prices, per account,
+/// strategy and instrument. Daily PnL restarts at a session boundary, by
+/// default midnight UTC. Clones share the same books.
+#[pyclass]
+#[derive(Clone)]
+pub struct PnlEngine {
+    state: Arc<RwLock<PnlState>>,
+}
+
+impl PnlEngine {
+    /// Refuses a fill the books can't take; a fill that passes is always
+    /// booked.
+    pub fn check_fill(quantity: Decimal, timestamp: Option<f64>) -> PyResult<()> {
+        if quantity <= Decimal::ZERO {
+            let err_msg = format!("Fill quantity must be positive, got {}", quantity);
+            error!("{}", err_msg);
+            return Err(PyErr::new::<pyo3::exceptions::PyValueError, _>(err_msg));
+        }
+        if let Some(timestamp) = timestamp.filter(|t| !t.is_finite()) {
+            let err_msg = format!("Fill timestamp must be finite, got {}", timestamp);
+            error!("{}", err_msg);
+            return Err(PyErr::new::<pyo3::exceptions::PyValueError, _>(err_msg));
+        }
+        Ok(())
+    }
+
+    /// Daily PnL of the books whose account, strategy and symbol pass `filter`.
+    pub fn daily_pnl_matching<F: Fn(Option<&str>, Option<&str>, &str) -> bool>(&self, filter: F) -> Decimal {
+        self.state.write().unwrap()
+            .summary(|k| filter(k.account.as_deref(), k.strategy_id.as_deref(), &k.symbol))
+            .daily
+    }
+
+    /// The symbol's mark, or its last price in the market data.
+    pub fn mark_price(&self, symbol: &str) -> Option<Decimal> {
+        self.state.read().unwrap().mark(symbol)
+    }
+
+    pub fn with_clock(cost_basis: CostBasis, clock: ClockHandle) -> Self {
+        PnlEngine {
+            state: Arc::new(RwLock::new(PnlState {
+                cost_basis,
+                books: HashMap::new(),
+                marks: HashMap::new(),
+                market_data: None,
+                instruments: None,
+                clock,
+                session_boundary: NaiveTime::MIN,
+                timezone: Tz::UTC,
+                session_start: None,
+            })),
+        }
+    }
+}
+
+#[pymethods]
+impl PnlEngine {
+    #[new]
+    #[pyo3(signature = (cost_basis=CostBasis::Fifo))]
+    pub fn new(cost_basis: CostBasis) -> Self {
+        PnlEngine::with_clock(cost_basis, ClockHandle::default())
+    }
+
+    #[getter]
+    pub fn cost_basis(&self) -> CostBasis {
+        self.state.read().unwrap().cost_basis
+    }
+
+    /// Daily PnL restarts at `time` (`HH:MM`) in `timezone` every day.
+    #[pyo3(signature = (time, timezone="UTC"))]
+    pub fn set_session_boundary(&self, time: &str, timezone: &str) -> PyResult<()> {
+        let session_boundary = parse_time(time)?;
+        let timezone: Tz = timezone.parse().map_err(|e| {
+            let err_msg = format!("Invalid timezone {}: {}", timezone, e);
+            error!("{}", err_msg);
+            PyErr::new::<pyo3::exceptions::PyValueError, _>(err_msg)
+        })?;
+        let mut state = self.state.write().unwrap();
+        state.session_boundary = session_boundary;
+        state.timezone = timezone;
+        Ok(())
+    }
+
+    pub fn set_clock(&self, clock: ClockHandle) {
+        self.state.write().unwrap().clock = clock;
+    }
+
+    /// Contract multipliers come from the registry; unknown symbols use 1.
+    pub fn set_instruments(&self, instruments: Option<InstrumentRegistry>) {
+        self.state.write().unwrap().instruments = instruments;
+    }
+
+    /// Marks symbols without an explicit mark at their last price.
+    pub fn set_market_data(&self, market_data: Option<MarketDataStore>) {
+        self.state.write().unwrap().market_data = market_data;
+    }
+
+    /// A mark after the session boundary first closes the old session at the
+    /// previous marks.
+    pub fn update_mark(&self, symbol: String, price: Decimal) {
+        let mut state = self.state.write().unwrap();
+        let now = state.clock.now();
+        state.roll(now);
+        state.marks.insert(symbol, price);
+    }
+
+    /// Books a fill of `quantity` at `price` against the order's account,
+    /// strategy and symbol, returning the PnL it realized.
+    #[pyo3(signature = (order, quantity, price, timestamp=None))]
+    pub fn apply_fill(&self, order: &Order, quantity: Decimal, price: Decimal, timestamp: Option<f64>) -> PyResult<Decimal> {
+        PnlEngine::check_fill(quantity, timestamp)?;
+        let mut state = self.state.write().unwrap();
+        let now = timestamp.unwrap_or_else(|| state.clock.now());
+        state.roll(now);
+        let key = BookKey {
+            account: order.account.clone(),
+            strategy_id: order.strategy_id.clone(),
+            symbol: order.symbol.clone(),
+        };
+        let cost_basis = state.cost_basis;
+        let multiplier = state.multiplier(&order.symbol);
+        let realized = state.books.entry(key).or_default()
+            .apply_fill(cost_basis, order.side.sign() * quantity, price, multiplier);
+        if !realized.is_zero() {
+            info!("Order {} realized {} in {}", order.order_id, realized, order.symbol);
+        }
+        Ok(realized)
+    }
+
+    #[pyo3(signature = (symbol, account=None, strategy_id=None))]
+    pub fn pnl(&self, symbol: String, account: Option<String>, strategy_id: Option<String>) -> PnlSummary {
+        let key = BookKey { account, strategy_id, symbol };
+        self.state.write().unwrap().summary(|k| *k == key)
+    }
+
+    /// Totals of one strategy across its instruments.
+    #[pyo3(signature = (strategy_id, account=None))]
+    pub fn strategy_pnl(&self, strategy_id: &str, account: Option<&str>) -> PnlSummary {
+        self.state.write().unwrap()
+            .summary(|k| k.strategy_id.as_deref() == Some(strategy_id) && k.account.as_deref() == account)
+    }
+
+    /// Totals of an account across its strategies and instruments.
+    #[pyo3(signature = (account=None))]
+    pub fn account_pnl(&self, account: Option<&str>) -> PnlSummary {
+        self.state.write().unwrap().summary(|k| k.account.as_deref() == account)
+    }
+
+    /// The account's PnL since the session boundary, as checked against
+    /// `max_daily_loss`.
+    #[pyo3(signature = (account=None))]
+    pub fn daily_pnl(&self, account: Option<&str>) -> Decimal {
+        self.account_pnl(account).daily
+    }
+}
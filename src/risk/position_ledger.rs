@@ -0,0 +1,258 @@
+use pyo3::prelude::*;
+use std::collections::HashMap;
+use std::sync::{Arc, RwLock};
+use log::{info, error};
+use crate::models::order::Order;
+use crate::models::numeric::Decimal;
+
+#[derive(Debug, Clone, Eq, PartialEq, Hash)]
+struct PositionKey {
+    account: Option<String>,
+    strategy_id: Option<String>,
+    symbol: String,
+}
+
+impl From<&Order> for PositionKey {
+    fn from(order: &Order) -> Self {
+        PositionKey {
+            account: order.account.clone(),
+            strategy_id: order.strategy_id.clone(),
+            symbol: order.symbol.clone(),
+        }
+    }
+}
+
+/// Holding of one instrument by one strategy within an account.
+#[pyclass]
+#[derive(Debug, Clone)]
+pub struct Position {
+    #[pyo3(get)]
+    pub account: Option<String>,
+    #[pyo3(get)]
+    pub strategy_id: Option<String>,
+    #[pyo3(get)]
+    pub symbol: String,
+    /// Positive when long, negative when short
+    #[pyo3(get)]
+    pub quantity: Decimal,
+    /// Average cost of the open quantity
+    #[pyo3(get)]
+    pub avg_price: Decimal,
+}
+
+impl Position {
+    fn new(key: &PositionKey) -> Self {
+        Position {
+            account: key.account.clone(),
+            strategy_id: key.strategy_id.clone(),
+            symbol: key.symbol.clone(),
+            quantity: Decimal::ZERO,
+            avg_price: Decimal::ZERO,
+        }
+    }
+
+    /// Adds a signed fill. Fills that reduce the position leave the average
+    /// cost alone; one that flips it starts again at the fill price. A fill
+    /// too large to average leaves the position unchanged.
+    fn apply_fill(&mut self, quantity: Decimal, price: Decimal) -> PyResult<()> {
+        let overflow = || {
+            let err_msg = format!("Fill of {} at {} overflows the {} position of {}", quantity, price, self.symbol, self.quantity);
+            error!("{}", err_msg);
+            PyErr::new::<pyo3::exceptions::PyValueError, _>(err_msg)
+        };
+        let new_quantity = self.quantity.checked_add(quantity).ok_or_else(overflow)?;
+        let avg_price = if self.quantity.is_zero() || self.quantity.is_sign_positive() == quantity.is_sign_positive() {
+            self.quantity.abs().checked_mul(self.avg_price)
+                .and_then(|cost| cost.checked_add(quantity.abs().checked_mul(price)?))
+                .and_then(|cost| cost.checked_div(new_quantity.abs()))
+                .ok_or_else(overflow)?
+        } else if new_quantity.is_zero() {
+            Decimal::ZERO
+        } else if new_quantity.is_sign_positive() != self.quantity.is_sign_positive() {
+            price
+        } else {
+            self.avg_price
+        };
+        self.quantity = new_quantity;
+        self.avg_price = avg_price;
+        Ok(())
+    }
+}
+
+#[pymethods]
+impl Position {
+    pub fn __str__(&self) -> String {
+        format!("Position(account={:?}, strategy_id={:?}, symbol={}, quantity={}, avg_price={})",
+                self.account, self.strategy_id, self.symbol, self.quantity, self.avg_price)
+    }
+}
+
+#[derive(Default)]
+struct LedgerState {
+    positions: HashMap<PositionKey, Position>,
+    // Signed quantity still working on each open order
+    pending: HashMap<u64, (PositionKey, Decimal)>,
+    // One-cancels-other groups, of which at most one order can trade
+    links: HashMap<u64, u64>,
+}
+
+/// Positions per account, strategy and instrument, kept up to date from
+/// fills, along with the quantity still working on open orders. Clones
+/// share the same ledger, so executors and risk validators see one book.
+#[pyclass]
+#[derive(Clone, Default)]
+pub struct PositionLedger {
+    state: Arc<RwLock<LedgerState>>,
+}
+
+impl PositionLedger {
+    /// Records how much of an order is still working; zero drops it.
+    pub fn set_pending(&self, order: &Order, leaves_quantity: Decimal) {
+        let mut state = self.state.write().unwrap();
+        if leaves_quantity.is_zero() {
+            state.pending.remove(&order.order_id);
+            state.links.remove(&order.order_id);
+        } else {
+            state.pending.insert(order.order_id, (PositionKey::from(order), order.side.sign() * leaves_quantity));
+        }
+    }
+
+    /// Marks orders that cancel each other, so that only the largest of them
+    /// on each side counts as working.
+    pub fn link(&self, group_id: u64, order_ids: &[u64]) {
+        let mut state = self.state.write().unwrap();
+        for order_id in order_ids {
+            state.links.insert(*order_id, group_id);
+        }
+    }
+
+    pub fn unlink(&self, order_id: u64) {
+        self.state.write().unwrap().links.remove(&order_id);
+    }
+
+    /// Working buy and sell quantity of the account in the symbol, leaving
+    /// out `exclude` so an order being amended isn't counted twice.
+    pub fn pending_excluding(&self, symbol: &str, account: Option<&str>, exclude: Option<u64>) -> (Decimal, Decimal) {
+        self.pending_matching(exclude, |a, _, s| s == symbol && a == account)
+    }
+
+    /// Sum of the positions whose account, strategy and symbol pass `filter`.
+    pub fn position_matching<F: Fn(Option<&str>, Option<&str>, &str) -> bool>(&self, filter: F) -> Decimal {
+        self.state.read().unwrap().positions.values()
+            .filter(|p| filter(p.account.as_deref(), p.strategy_id.as_deref(), &p.symbol))
+            .map(|p| p.quantity)
+            .sum()
+    }
+
+    /// Net quantity and average cost by symbol of the positions whose
+    /// account, strategy and symbol pass `filter`.
+    pub fn holdings_matching<F: Fn(Option<&str>, Option<&str>, &str) -> bool>(&self, filter: F) -> HashMap<String, (Decimal, Decimal)> {
+        let state = self.state.read().unwrap();
+        let mut totals: HashMap<String, (Decimal, Decimal, Decimal)> = HashMap::new();
+        for p in state.positions.values().filter(|p| filter(p.account.as_deref(), p.strategy_id.as_deref(), &p.symbol)) {
+            let (quantity, size, cost) = totals.entry(p.symbol.clone()).or_default();
+            *quantity += p.quantity;
+            *size += p.quantity.abs();
+            *cost += p.quantity.abs() * p.avg_price;
+        }
+        totals.into_iter()
+            .map(|(symbol, (quantity, size, cost))| (symbol, (quantity, cost.checked_div(size).unwrap_or_default())))
+            .collect()
+    }
+
+    /// Working buy and sell quantity of the orders whose account, strategy
+    /// and symbol pass `filter`. Linked orders count once per side, and
+    /// those linked to `exclude` not at all, since they would be cancelled
+    /// if it traded.
+    pub fn pending_matching<F: Fn(Option<&str>, Option<&str>, &str) -> bool>(&self, exclude: Option<u64>, filter: F) -> (Decimal, Decimal) {
+        let state = self.state.read().unwrap();
+        let excluded_group = exclude.and_then(|id| state.links.get(&id));
+        let (mut buys, mut sells) = (Decimal::ZERO, Decimal::ZERO);
+        let mut linked: HashMap<u64, (Decimal, Decimal)> = HashMap::new();
+        for (id, (key, quantity)) in &state.pending {
+            if Some(*id) == exclude || !filter(key.account.as_deref(), key.strategy_id.as_deref(), &key.symbol) {
+                continue;
+            }
+            let (buy, sell) = if quantity.is_sign_positive() { (*quantity, Decimal::ZERO) } else { (Decimal::ZERO, -*quantity) };
+            match state.links.get(id) {
+                Some(group) if Some(group) == excluded_group => {},
+                Some(group) => {
+                    let (group_buys, group_sells) = linked.entry(*group).or_default();
+                    *group_buys = (*group_buys).max(buy);
+                    *group_sells = (*group_sells).max(sell);
+                },
+                None => {
+                    buys += buy;
+                    sells += sell;
+                },
+            }
+        }
+        linked.values().fold((buys, sells), |(buys, sells), (b, s)| (buys + b, sells + s))
+    }
+}
+
+#[pymethods]
+impl PositionLedger {
+    #[new]
+    pub fn new() -> Self {
+        PositionLedger::default()
+    }
+
+    /// Books a fill of `quantity` at `price` against the order's account,
+    /// strategy and symbol.
+    pub fn apply_fill(&self, order: &Order, quantity: Decimal, price: Decimal) -> PyResult<()> {
+        if quantity <= Decimal::ZERO {
+            let err_msg = format!("Fill quantity must be positive, got {}", quantity);
+            error!("{}", err_msg);
+            return Err(PyErr::new::<pyo3::exceptions::PyValueError, _>(err_msg));
+        }
+        let key = PositionKey::from(order);
+        let mut state = self.state.write().unwrap();
+        let mut position = state.positions.get(&key).cloned().unwrap_or_else(|| Position::new(&key));
+        position.apply_fill(order.side.sign() * quantity, price)?;
+        info!("Position in {} now {} after fill of order {}", order.symbol, position.quantity, order.order_id);
+        state.positions.insert(key, position);
+        Ok(())
+    }
+
+    /// Overwrites a position, e.g. from the broker's start-of-day positions.
+    #[pyo3(signature = (symbol, quantity, avg_price=Decimal::ZERO, account=None, strategy_id=None))]
+    pub fn set_position(&self, symbol: String, quantity: Decimal, avg_price: Decimal, account: Option<String>, strategy_id: Option<String>) {
+        let key = PositionKey { account, strategy_id, symbol };
+        let mut position = Position::new(&key);
+        position.quantity = quantity;
+        position.avg_price = avg_price;
+        self.state.write().unwrap().positions.insert(key, position);
+    }
+
+    #[pyo3(signature = (symbol, account=None, strategy_id=None))]
+    pub fn get_position(&self, symbol: String, account: Option<String>, strategy_id: Option<String>) -> Option<Position> {
+        let key = PositionKey { account, strategy_id, symbol };
+        self.state.read().unwrap().positions.get(&key).cloned()
+    }
+
+    /// The account's position in the symbol across all of its strategies.
+    #[pyo3(signature = (symbol, account=None))]
+    pub fn net_position(&self, symbol: &str, account: Option<&str>) -> Decimal {
+        self.position_matching(|a, _, s| s == symbol && a == account)
+    }
+
+    /// Working `(buy, sell)` quantity of the account in the symbol.
+    #[pyo3(signature = (symbol, account=None))]
+    pub fn pending(&self, symbol: &str, account: Option<&str>) -> (Decimal, Decimal) {
+        self.pending_excluding(symbol, account, None)
+    }
+
+    /// Every position, or only those of one account.
+    #[pyo3(signature = (account=None))]
+    pub fn positions(&self, account: Option<&str>) -> Vec<Position> {
+        self.state.read().unwrap().positions.values()
+            .filter(|p| account.is_none() || p.account.as_deref() == account)
+            .cloned()
+            .collect()
+    }
+
+    pub fn __len__(&self) -> usize {
+        self.state.read().unwrap().positions.len()
+    }
+}
@@ -0,0 +1,159 @@
+use pyo3::prelude::*;
+use pyo3::create_exception;
+use pyo3::exceptions::PyValueError;
+use std::fmt;
+use crate::models::numeric::Decimal;
+use crate::risk::limits::LimitScope;
+
+create_exception!(quanta_engine, RiskLimitError, PyValueError, "An order breached one or more risk limits; `decision` holds the details.");
+
+/// The limits `RiskValidator` checks.
+#[pyclass]
+#[derive(Debug, Clone, Copy, Eq, PartialEq, Hash)]
+pub enum RiskLimit {
+    /// Order quantity against the maximum position size
+    OrderQuantity,
+    OrderValue,
+    DailyLoss,
+    /// Position after the order, and after every working order on the same side
+    Position,
+    /// A plain sell larger than the long it closes
+    SellBeyondLong,
+    /// A buy-to-cover larger than the short it closes
+    CoverBeyondShort,
+    /// Market value of long plus short holdings after the order
+    GrossExposure,
+    /// Market value of long less short holdings after the order, either way
+    NetExposure,
+    LongExposure,
+    ShortExposure,
+    /// Gross value in the order's sector as a percentage of equity
+    SectorConcentration,
+    /// Instruments held after the order
+    OpenPositions,
+}
+
+impl fmt::Display for RiskLimit {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            RiskLimit::OrderQuantity => write!(f, "order quantity"),
+            RiskLimit::OrderValue => write!(f, "order value"),
+            RiskLimit::DailyLoss => write!(f, "daily loss"),
+            RiskLimit::Position => write!(f, "position"),
+            RiskLimit::SellBeyondLong => write!(f, "long position to sell"),
+            RiskLimit::CoverBeyondShort => write!(f, "short position to cover"),
+            RiskLimit::GrossExposure => write!(f, "gross exposure"),
+            RiskLimit::NetExposure => write!(f, "net exposure"),
+            RiskLimit::LongExposure => write!(f, "long exposure"),
+            RiskLimit::ShortExposure => write!(f, "short exposure"),
+            RiskLimit::SectorConcentration => write!(f, "sector concentration %"),
+            RiskLimit::OpenPositions => write!(f, "open positions"),
+        }
+    }
+}
+
+/// One limit as checked against an order. Negative headroom means the
+/// order breaches it.
+#[pyclass]
+#[derive(Debug, Clone, PartialEq)]
+pub struct LimitCheck {
+    #[pyo3(get)]
+    pub limit: RiskLimit,
+    /// Scope the limit was set on; `None` for the validator's own limits
+    #[pyo3(get)]
+    pub scope: Option<LimitScope>,
+    /// Account, strategy, symbol, sector or asset class of the scope
+    #[pyo3(get)]
+    pub scope_key: Option<String>,
+    #[pyo3(get)]
+    pub limit_value: Decimal,
+    #[pyo3(get)]
+    pub observed: Decimal,
+    #[pyo3(get)]
+    pub headroom: Decimal,
+}
+
+impl LimitCheck {
+    pub fn new(limit: RiskLimit, limit_value: Decimal, observed: Decimal) -> Self {
+        LimitCheck {
+            limit,
+            scope: None,
+            scope_key: None,
+            limit_value,
+            observed,
+            headroom: limit_value.saturating_sub(observed),
+        }
+    }
+
+    pub fn for_scope(mut self, scope: LimitScope, key: &str) -> Self {
+        self.scope = Some(scope);
+        self.scope_key = (scope != LimitScope::Firm).then(|| key.to_string());
+        self
+    }
+}
+
+#[pymethods]
+impl LimitCheck {
+    #[getter]
+    pub fn breached(&self) -> bool {
+        self.headroom < Decimal::ZERO
+    }
+
+    pub fn __str__(&self) -> String {
+        let scope = match (&self.scope, &self.scope_key) {
+            (Some(scope), Some(key)) => format!("{:?} {} ", scope, key),
+            (Some(scope), None) => format!("{:?} ", scope),
+            _ => String::new(),
+        };
+        format!("{}{} {} against a limit of {} ({} headroom)", scope, self.limit, self.observed, self.limit_value, self.headroom)
+    }
+}
+
+/// Outcome of the risk checks on one order, with every limit that was checked.
+#[pyclass]
+#[derive(Debug, Clone)]
+pub struct RiskDecision {
+    #[pyo3(get)]
+    pub order_id: u64,
+    #[pyo3(get)]
+    pub checks: Vec<LimitCheck>,
+}
+
+impl RiskDecision {
+    pub fn new(order_id: u64, checks: Vec<LimitCheck>) -> Self {
+        RiskDecision { order_id, checks }
+    }
+
+    /// Raises `RiskLimitError` carrying this decision if any limit was breached.
+    pub fn into_result(self, py: Python) -> PyResult<RiskDecision> {
+        if self.approved() {
+            return Ok(self);
+        }
+        let err = RiskLimitError::new_err(format!("Order {} rejected by risk validator: {}", self.order_id, self.__str__()));
+        err.value(py).setattr("decision", self.into_py(py))?;
+        Err(err)
+    }
+}
+
+#[pymethods]
+impl RiskDecision {
+    #[getter]
+    pub fn approved(&self) -> bool {
+        self.checks.iter().all(|check| !check.breached())
+    }
+
+    pub fn breaches(&self) -> Vec<LimitCheck> {
+        self.checks.iter().filter(|check| check.breached()).cloned().collect()
+    }
+
+    pub fn __bool__(&self) -> bool {
+        self.approved()
+    }
+
+    pub fn __str__(&self) -> String {
+        if self.approved() {
+            return "approved".to_string();
+        }
+        self.breaches().iter().map(|check| check.__str__()).collect::<Vec<_>>().join("; ")
+    }
+}
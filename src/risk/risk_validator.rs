@@ -1,41 +1,234 @@
 use pyo3::prelude::*;
-use crate::models::order::Order;
+use crate::models::order::{Order, Side};
+use crate::models::numeric::{extract_limit, notional, Decimal};
+use crate::models::instrument::InstrumentRegistry;
+use crate::risk::position_ledger::PositionLedger;
+use crate::risk::pnl_engine::PnlEngine;
+use crate::risk::risk_decision::{LimitCheck, RiskDecision, RiskLimit};
+use crate::risk::limits::{LimitBook, LimitScope, ScopeLimits};
+use crate::risk::exposure::Exposure;
+use log::warn;
+use std::collections::HashMap;
 
 #[pyclass]
+#[derive(Clone)]
 pub struct RiskValidator {
-    max_position_size: f64,
-    max_daily_loss: f64,
-    max_order_value: f64,
+    max_position_size: Decimal,
+    max_daily_loss: Decimal,
+    max_order_value: Decimal,
+    instruments: Option<InstrumentRegistry>,
+    ledger: Option<PositionLedger>,
+    pnl: Option<PnlEngine>,
+    limits: LimitBook,
+}
+
+impl RiskValidator {
+    /// Order value in account currency, using the instrument's multiplier when known.
+    fn order_notional(&self, order: &Order) -> Decimal {
+        match self.instruments.as_ref().and_then(|registry| registry.get(&order.symbol)) {
+            Some(instrument) => instrument.notional(order.quantity, order.price),
+            None => notional(order.quantity, order.price),
+        }
+    }
+
+    /// Position of the scope in `symbol` with its working buy and sell
+    /// quantity, from the ledger. Without a ledger only the account
+    /// position the caller gave is known.
+    fn scope_position(&self, scope: LimitScope, key: &str, symbol: &str, exclude: Option<u64>, fallback: Decimal) -> (Decimal, Decimal, Decimal) {
+        let Some(ledger) = &self.ledger else { return (fallback, Decimal::ZERO, Decimal::ZERO) };
+        let instruments = self.instruments.as_ref();
+        let in_scope = |a: Option<&str>, s: Option<&str>, sym: &str| sym == symbol && scope.contains(key, a, s, sym, instruments);
+        let (pending_buys, pending_sells) = ledger.pending_matching(exclude, in_scope);
+        (ledger.position_matching(in_scope), pending_buys, pending_sells)
+    }
+
+    /// Daily PnL of the scope from the PnL engine, or the account's figure
+    /// without one.
+    fn scope_daily_pnl(&self, scope: LimitScope, key: &str, fallback: Decimal) -> Decimal {
+        let Some(pnl) = &self.pnl else { return fallback };
+        let instruments = self.instruments.as_ref();
+        pnl.daily_pnl_matching(|a, s, sym| scope.contains(key, a, s, sym, instruments))
+    }
+
+    /// Net holdings of the scope by symbol once the order fills, each priced
+    /// at its mark, else the order's price, else its average cost.
+    fn projected_holdings(&self, scope: LimitScope, key: &str, order: Option<&Order>) -> HashMap<String, (Decimal, Decimal)> {
+        let instruments = self.instruments.as_ref();
+        let mut holdings = self.ledger.as_ref()
+            .map(|ledger| ledger.holdings_matching(|a, s, sym| scope.contains(key, a, s, sym, instruments)))
+            .unwrap_or_default();
+        if let Some(order) = order {
+            let (quantity, price) = holdings.entry(order.symbol.clone()).or_default();
+            *quantity += order.signed_quantity();
+            if !order.price.is_zero() {
+                *price = order.price;
+            }
+        }
+        if let Some(pnl) = &self.pnl {
+            for (symbol, (_, price)) in holdings.iter_mut() {
+                if let Some(mark) = pnl.mark_price(symbol) {
+                    *price = mark;
+                }
+            }
+        }
+        holdings
+    }
+
+    #[allow(clippy::too_many_arguments)]
+    fn check_scope(&self, order: &Order, scope: LimitScope, key: &str, limits: &ScopeLimits, current_position: Decimal,
+                   daily_pnl: Decimal, checks: &mut Vec<LimitCheck>) {
+        let mut push = |check: LimitCheck| checks.push(check.for_scope(scope, key));
+        if let Some(max_order_quantity) = limits.max_order_quantity {
+            push(LimitCheck::new(RiskLimit::OrderQuantity, max_order_quantity, order.quantity));
+        }
+        if let Some(max_order_value) = limits.max_order_value {
+            push(LimitCheck::new(RiskLimit::OrderValue, max_order_value, self.order_notional(order)));
+        }
+        if let Some(max_daily_loss) = limits.max_daily_loss {
+            push(LimitCheck::new(RiskLimit::DailyLoss, max_daily_loss, -self.scope_daily_pnl(scope, key, daily_pnl)));
+        }
+        if let Some(max_position) = limits.max_position {
+            let (position, pending_buys, pending_sells) = self.scope_position(scope, key, &order.symbol, Some(order.order_id), current_position);
+            let new_position = position + order.signed_quantity();
+            let worst_position = if order.side.is_buy() { new_position + pending_buys } else { new_position - pending_sells };
+            push(LimitCheck::new(RiskLimit::Position, max_position, new_position.abs().max(worst_position.abs())));
+        }
+        if limits.has_exposure_limits() {
+            let exposure = Exposure::of(&self.projected_holdings(scope, key, Some(order)), self.instruments.as_ref());
+            let sector = self.instruments.as_ref().and_then(|registry| registry.get(&order.symbol)).and_then(|i| i.sector);
+            limits.exposure_checks(&exposure, sector.as_deref()).into_iter().for_each(push);
+        }
+    }
 }
 
 #[pymethods]
 impl RiskValidator {
     #[new]
-    fn new(max_position_size: f64, max_daily_loss: f64, max_order_value: f64) -> Self {
-        RiskValidator {
-            max_position_size,
-            max_daily_loss,
-            max_order_value,
-        }
+    fn new(max_position_size: &PyAny, max_daily_loss: &PyAny, max_order_value: &PyAny) -> PyResult<Self> {
+        Ok(RiskValidator {
+            max_position_size: extract_limit(max_position_size)?,
+            max_daily_loss: extract_limit(max_daily_loss)?,
+            max_order_value: extract_limit(max_order_value)?,
+            instruments: None,
+            ledger: None,
+            pnl: None,
+            limits: LimitBook::new(),
+        })
     }
 
-    fn validate_order(&self, order: &Order, current_position: f64, daily_pnl: f64) -> PyResult<bool> {
-        if order.quantity.abs() > self.max_position_size {
-            return Ok(false);
+    fn set_instruments(&mut self, instruments: Option<InstrumentRegistry>) {
+        self.instruments = instruments;
+    }
+
+    /// Reads positions and working orders from the ledger, so callers no
+    /// longer have to track them.
+    pub fn set_ledger(&mut self, ledger: Option<PositionLedger>) {
+        self.ledger = ledger;
+    }
+
+    pub fn get_ledger(&self) -> Option<PositionLedger> {
+        self.ledger.clone()
+    }
+
+    /// Limits by firm, account, strategy, instrument, sector and asset
+    /// class, checked on top of the validator's own. Changes to the book
+    /// apply at once.
+    pub fn get_limits(&self) -> LimitBook {
+        self.limits.clone()
+    }
+
+    /// Shares a limit book between validators.
+    pub fn set_limits(&mut self, limits: LimitBook) {
+        self.limits = limits;
+    }
+
+    /// Current use of a scope's limits, without an order. Position limits
+    /// need a `symbol` unless the scope is an instrument, and concentration
+    /// is reported for the scope's largest sector.
+    #[pyo3(signature = (scope, key=None, symbol=None))]
+    pub fn usage(&self, scope: LimitScope, key: Option<String>, symbol: Option<String>) -> PyResult<Vec<LimitCheck>> {
+        let Some(limits) = self.limits.get(scope, key.clone())? else { return Ok(Vec::new()) };
+        let key = key.unwrap_or_default();
+        let key = if scope == LimitScope::Firm { String::new() } else { key };
+        let mut checks = Vec::new();
+        if let Some(max_daily_loss) = limits.max_daily_loss {
+            checks.push(LimitCheck::new(RiskLimit::DailyLoss, max_daily_loss, -self.scope_daily_pnl(scope, &key, Decimal::ZERO)).for_scope(scope, &key));
+        }
+        let symbol = symbol.or_else(|| (scope == LimitScope::Instrument).then(|| key.clone()));
+        if let (Some(max_position), Some(symbol)) = (limits.max_position, symbol) {
+            let (position, pending_buys, pending_sells) = self.scope_position(scope, &key, &symbol, None, Decimal::ZERO);
+            let worst = (position + pending_buys).abs().max((position - pending_sells).abs());
+            checks.push(LimitCheck::new(RiskLimit::Position, max_position, worst).for_scope(scope, &key));
         }
+        if limits.has_exposure_limits() {
+            let exposure = Exposure::of(&self.projected_holdings(scope, &key, None), self.instruments.as_ref());
+            checks.extend(limits.exposure_checks(&exposure, exposure.largest_sector()).into_iter().map(|check| check.for_scope(scope, &key)));
+        }
+        Ok(checks)
+    }
+
+    /// Checks `max_daily_loss` against the account's daily PnL from the engine.
+    pub fn set_pnl(&mut self, pnl: Option<PnlEngine>) {
+        self.pnl = pnl;
+    }
+
+    pub fn get_pnl(&self) -> Option<PnlEngine> {
+        self.pnl.clone()
+    }
+
+    /// Checks every limit and reports each one's value, the observed value and
+    /// the headroom left. Without a `current_position` the account's position
+    /// comes from the ledger, and without a `daily_pnl` from the PnL engine.
+    /// With a ledger, working orders on the same side as the order count as
+    /// filled when checking the position limits.
+    #[pyo3(signature = (order, current_position=None, daily_pnl=None))]
+    pub fn check_order(&self, order: &Order, current_position: Option<Decimal>, daily_pnl: Option<Decimal>) -> RiskDecision {
+        let mut checks = vec![
+            LimitCheck::new(RiskLimit::OrderQuantity, self.max_position_size, order.quantity),
+            LimitCheck::new(RiskLimit::OrderValue, self.max_order_value, self.order_notional(order)),
+        ];
+
+        let daily_pnl = match (daily_pnl, &self.pnl) {
+            (Some(pnl), _) => pnl,
+            (None, Some(engine)) => engine.daily_pnl(order.account.as_deref()),
+            (None, None) => Decimal::ZERO,
+        };
+        checks.push(LimitCheck::new(RiskLimit::DailyLoss, self.max_daily_loss, -daily_pnl));
 
-        if order.quantity * order.price > self.max_order_value {
-            return Ok(false);
+        let current_position = match (current_position, &self.ledger) {
+            (Some(position), _) => position,
+            (None, Some(ledger)) => ledger.net_position(&order.symbol, order.account.as_deref()),
+            (None, None) => Decimal::ZERO,
+        };
+        let (pending_buys, pending_sells) = self.ledger.as_ref()
+            .map(|ledger| ledger.pending_excluding(&order.symbol, order.account.as_deref(), Some(order.order_id)))
+            .unwrap_or_default();
+        let new_position = current_position + order.signed_quantity();
+        let worst_position = if order.side.is_buy() { new_position + pending_buys } else { new_position - pending_sells };
+        checks.push(LimitCheck::new(RiskLimit::Position, self.max_position_size, new_position.abs().max(worst_position.abs())));
+
+        // A plain sell may only close a long and a buy-to-cover may only close a short
+        match order.side {
+            Side::Sell => checks.push(LimitCheck::new(RiskLimit::SellBeyondLong, current_position - pending_sells, order.quantity)),
+            Side::BuyToCover => checks.push(LimitCheck::new(RiskLimit::CoverBeyondShort, -(current_position + pending_buys), order.quantity)),
+            Side::Buy | Side::SellShort => {},
         }
 
-        if daily_pnl < -self.max_daily_loss {
-            return Ok(false);
+        // The order has to fit within every scope it belongs to
+        for (scope, key, limits) in self.limits.scopes_for(order, self.instruments.as_ref()) {
+            self.check_scope(order, scope, &key, &limits, current_position, daily_pnl, &mut checks);
         }
 
-        if (current_position + order.quantity).abs() > self.max_position_size {
-            return Ok(false);
+        let decision = RiskDecision::new(order.order_id, checks);
+        if !decision.approved() {
+            warn!("Order {} breached risk limits: {}", order.order_id, decision.__str__());
         }
+        decision
+    }
 
-        Ok(true)
+    /// Whether the order passes every limit; `check_order` says which failed.
+    #[pyo3(signature = (order, current_position=None, daily_pnl=None))]
+    pub fn validate_order(&self, order: &Order, current_position: Option<Decimal>, daily_pnl: Option<Decimal>) -> PyResult<bool> {
+        Ok(self.check_order(order, current_position, daily_pnl).approved())
     }
 }
\ No newline at end of file
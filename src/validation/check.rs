@@ -0,0 +1,100 @@
+use pyo3::prelude::*;
+use std::sync::Arc;
+use log::{info, error};
+use crate::models::order::Order;
+use crate::models::instrument::Instrument;
+use crate::validation::error::ValidationError;
+use crate::validation::report::{Severity, Violation};
+
+/// An extra check run by `OrderValidator` after its built-in checks.
+pub trait ValidationCheck: Send + Sync {
+    /// Name used to list and remove the check
+    fn name(&self) -> &str;
+
+    /// Violations found on the order; an empty vec means it passed.
+    fn check(&self, order: &Order, instrument: &Instrument) -> Vec<Violation>;
+}
+
+/// Adapts a Python callable `check(order, instrument)`. It passes by returning
+/// `None` or `True`, fails with `False` or a message, and may return a
+/// `(Severity, message)` tuple to record a warning instead. A check that
+/// raises fails the order, so a broken compliance rule never lets orders
+/// through.
+pub struct PythonCheck {
+    name: String,
+    callable: PyObject,
+}
+
+impl PythonCheck {
+    pub fn new(name: String, callable: PyObject) -> Self {
+        PythonCheck { name, callable }
+    }
+
+    fn violation(&self, severity: Severity, message: String) -> Violation {
+        Violation::new(severity, ValidationError::CustomCheck { check: self.name.clone(), message })
+    }
+}
+
+impl ValidationCheck for PythonCheck {
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn check(&self, order: &Order, instrument: &Instrument) -> Vec<Violation> {
+        Python::with_gil(|py| {
+            let result = match self.callable.call1(py, (order.clone(), instrument.clone())) {
+                Ok(result) => result,
+                Err(e) => {
+                    error!("Validation check {} raised: {}", self.name, e);
+                    return vec![self.violation(Severity::Error, format!("check raised {}", e))];
+                },
+            };
+            let result = result.as_ref(py);
+
+            if result.is_none() {
+                Vec::new()
+            } else if let Ok(passed) = result.extract::<bool>() {
+                if passed { Vec::new() } else { vec![self.violation(Severity::Error, "check failed".to_string())] }
+            } else if let Ok(message) = result.extract::<String>() {
+                vec![self.violation(Severity::Error, message)]
+            } else if let Ok((severity, message)) = result.extract::<(Severity, String)>() {
+                vec![self.violation(severity, message)]
+            } else {
+                let message = format!("check returned an unsupported value: {}", result);
+                error!("Validation check {} {}", self.name, message);
+                vec![self.violation(Severity::Error, message)]
+            }
+        })
+    }
+}
+
+/// Custom checks in the order they run: by priority, then by registration.
+#[derive(Clone, Default)]
+pub struct CheckRegistry {
+    checks: Vec<(i32, Arc<dyn ValidationCheck>)>,
+}
+
+impl CheckRegistry {
+    /// Adds a check, replacing any existing check with the same name.
+    pub fn add(&mut self, check: Arc<dyn ValidationCheck>, priority: i32) {
+        self.remove(check.name());
+        info!("Registered validation check {} with priority {}", check.name(), priority);
+        // Insert after every check with the same or a lower priority
+        let position = self.checks.iter().position(|(p, _)| *p > priority).unwrap_or(self.checks.len());
+        self.checks.insert(position, (priority, check));
+    }
+
+    pub fn remove(&mut self, name: &str) -> bool {
+        let before = self.checks.len();
+        self.checks.retain(|(_, check)| check.name() != name);
+        self.checks.len() != before
+    }
+
+    pub fn names(&self) -> Vec<String> {
+        self.checks.iter().map(|(_, check)| check.name().to_string()).collect()
+    }
+
+    pub fn run(&self, order: &Order, instrument: &Instrument) -> Vec<Violation> {
+        self.checks.iter().flat_map(|(_, check)| check.check(order, instrument)).collect()
+    }
+}
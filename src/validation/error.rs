@@ -0,0 +1,321 @@
+use pyo3::prelude::*;
+use pyo3::create_exception;
+use pyo3::exceptions::PyValueError;
+use thiserror::Error;
+use crate::models::order::{OrderType, Side, TimeInForce};
+use crate::models::numeric::Decimal;
+use crate::models::market_data::ReferencePrice;
+use crate::calendar::trading_calendar::TradingSession;
+use crate::validation::order_validator::RuleLayer;
+use crate::execution::throttle::ThrottleScope;
+
+create_exception!(quanta_engine, OrderValidationError, PyValueError, "Base class for every order validation failure.");
+create_exception!(quanta_engine, UnknownSymbolError, OrderValidationError);
+create_exception!(quanta_engine, NonPositiveQuantityError, OrderValidationError);
+create_exception!(quanta_engine, LotSizeError, OrderValidationError);
+create_exception!(quanta_engine, TickSizeError, OrderValidationError);
+create_exception!(quanta_engine, QuantityOutOfRangeError, OrderValidationError);
+create_exception!(quanta_engine, PriceOutOfRangeError, OrderValidationError);
+create_exception!(quanta_engine, NoRuleForTypeError, OrderValidationError);
+create_exception!(quanta_engine, StopPriceRequiredError, OrderValidationError);
+create_exception!(quanta_engine, StopPriceNotAllowedError, OrderValidationError);
+create_exception!(quanta_engine, InvalidTrailError, OrderValidationError);
+create_exception!(quanta_engine, TrailNotAllowedError, OrderValidationError);
+create_exception!(quanta_engine, MissingExpireTimeError, OrderValidationError);
+create_exception!(quanta_engine, UnexpectedExpireTimeError, OrderValidationError);
+create_exception!(quanta_engine, UnsupportedTimeInForceError, OrderValidationError);
+create_exception!(quanta_engine, InstrumentExpiredError, OrderValidationError);
+create_exception!(quanta_engine, ExpireTimeInPastError, OrderValidationError);
+create_exception!(quanta_engine, OrderTooOldError, OrderValidationError);
+create_exception!(quanta_engine, PriceCollarError, OrderValidationError);
+create_exception!(quanta_engine, AdvExceededError, OrderValidationError);
+create_exception!(quanta_engine, OutsideTradingHoursError, OrderValidationError);
+create_exception!(quanta_engine, RestrictedSymbolError, OrderValidationError);
+create_exception!(quanta_engine, LocateRequiredError, OrderValidationError);
+create_exception!(quanta_engine, SelfTradeError, OrderValidationError);
+create_exception!(quanta_engine, ThrottledError, OrderValidationError);
+create_exception!(quanta_engine, DuplicateOrderError, OrderValidationError);
+create_exception!(quanta_engine, CustomCheckError, OrderValidationError);
+
+/// Why `OrderValidator` refused an order. Each variant is raised in Python as
+/// its own `OrderValidationError` subclass with the fields as attributes.
+#[derive(Debug, Error, Clone, PartialEq)]
+pub enum ValidationError {
+    #[error("Invalid symbol: {symbol}")]
+    UnknownSymbol { symbol: String },
+
+    #[error("Order quantity must be positive, got {quantity} (use side {side} to set direction)")]
+    NonPositiveQuantity { quantity: Decimal, side: Side },
+
+    #[error("Order quantity {quantity} is not a multiple of the lot size {lot_size} for {symbol}")]
+    LotSize { symbol: String, quantity: Decimal, lot_size: Decimal },
+
+    #[error("Order price {price} is not a multiple of the tick size {tick_size} for {symbol}")]
+    TickSize { symbol: String, price: Decimal, tick_size: Decimal },
+
+    #[error("Order quantity {quantity} is {} the {bound} {limit} set by the {rule}", direction(.bound))]
+    QuantityOutOfRange { quantity: Decimal, bound: &'static str, limit: Decimal, layer: RuleLayer, rule: String },
+
+    #[error("Order {field} {price} is {} the {bound} {limit} set by the {rule}", direction(.bound))]
+    PriceOutOfRange { field: &'static str, price: Decimal, bound: &'static str, limit: Decimal, layer: RuleLayer, rule: String },
+
+    #[error("No validation rule for order type: {order_type:?}")]
+    NoRuleForType { order_type: OrderType },
+
+    #[error("{order_type} orders require a stop price")]
+    StopPriceRequired { order_type: OrderType },
+
+    #[error("Stop price is not valid for {order_type} orders")]
+    StopPriceNotAllowed { order_type: OrderType, stop_price: Decimal },
+
+    #[error("{order_type} orders require either a positive trail amount or a trail percent below 100")]
+    InvalidTrail { order_type: OrderType, trail_amount: Option<Decimal>, trail_percent: Option<Decimal> },
+
+    #[error("Trail amount and percent are not valid for {order_type} orders")]
+    TrailNotAllowed { order_type: OrderType },
+
+    #[error("GTD orders require an expire time")]
+    MissingExpireTime,
+
+    #[error("Expire time is only valid for GTD orders, not {time_in_force}")]
+    UnexpectedExpireTime { time_in_force: TimeInForce, expire_time: f64 },
+
+    #[error("Time in force {time_in_force} is not supported for {order_type} orders")]
+    UnsupportedTimeInForce { time_in_force: TimeInForce, order_type: OrderType },
+
+    #[error("Instrument {symbol} has expired")]
+    InstrumentExpired { symbol: String, expiry: Option<f64> },
+
+    #[error("Order expire time {expire_time} is already in the past")]
+    ExpireTimeInPast { expire_time: f64 },
+
+    #[error("Order is too old: {age:.3}s exceeds the maximum age of {max_age}s")]
+    OrderTooOld { age: f64, max_age: f64 },
+
+    #[error("Order price {price} for {symbol} is {deviation_percent}% ({deviation_ticks} ticks) away from the {reference:?} price {reference_price}, outside the price collar")]
+    PriceCollarBreached { symbol: String, price: Decimal, reference: ReferencePrice, reference_price: Decimal, deviation_percent: Decimal, deviation_ticks: Decimal },
+
+    #[error("No {reference:?} price for {symbol}, so the price collar was not applied")]
+    MissingReferencePrice { symbol: String, reference: ReferencePrice },
+
+    #[error("Order notional {notional} for {symbol} exceeds {max_multiple} times the average daily traded value of {adv_notional}")]
+    AdvExceeded { symbol: String, notional: Decimal, adv_notional: Decimal, max_multiple: Decimal },
+
+    #[error("{exchange} is in its {session} session, so {symbol} cannot trade{}", if *.extended_hours { "" } else { " without extended hours" })]
+    OutsideTradingHours { symbol: String, exchange: String, session: TradingSession, extended_hours: bool },
+
+    #[error("{symbol} is on the restricted list{}", .reason.as_ref().map(|r| format!(": {}", r)).unwrap_or_default())]
+    RestrictedSymbol { symbol: String, reason: Option<String> },
+
+    /// Only ever reported as a warning
+    #[error("{symbol} is on the watch list{}", .reason.as_ref().map(|r| format!(": {}", r)).unwrap_or_default())]
+    WatchListSymbol { symbol: String, reason: Option<String> },
+
+    /// Only ever reported as a warning
+    #[error("{symbol} is hard to borrow; shorting {short_quantity}")]
+    HardToBorrow { symbol: String, short_quantity: Decimal },
+
+    #[error("Short sale of {short_quantity} {symbol} needs a locate, only {available} available")]
+    LocateRequired { symbol: String, short_quantity: Decimal, available: Decimal },
+
+    #[error("Order in {symbol} would trade against resting order {resting_order_id} of the same account")]
+    SelfTrade { symbol: String, resting_order_id: u64 },
+
+    #[error("Order rate for {scope:?} {key} is over its limit of {per_second} per second")]
+    Throttled { scope: ThrottleScope, key: String, per_second: f64 },
+
+    #[error("Order in {symbol} repeats order {previous_order_id} sent within the last {window}s")]
+    DuplicateOrder { symbol: String, previous_order_id: u64, window: f64 },
+
+    #[error("Check {check} failed: {message}")]
+    CustomCheck { check: String, message: String },
+
+    /// Only ever reported as a warning
+    #[error("Order {field} {value} is close to the {bound} {limit} set by the {rule}")]
+    NearBandEdge { field: &'static str, value: Decimal, bound: &'static str, limit: Decimal, layer: RuleLayer, rule: String },
+}
+
+fn direction(bound: &str) -> &'static str {
+    if bound == "minimum" { "below" } else { "above" }
+}
+
+impl ValidationError {
+    /// Stable identifier for the failure, matching the Python exception name.
+    pub fn code(&self) -> &'static str {
+        match self {
+            ValidationError::UnknownSymbol { .. } => "UnknownSymbol",
+            ValidationError::NonPositiveQuantity { .. } => "NonPositiveQuantity",
+            ValidationError::LotSize { .. } => "LotSize",
+            ValidationError::TickSize { .. } => "TickSize",
+            ValidationError::QuantityOutOfRange { .. } => "QuantityOutOfRange",
+            ValidationError::PriceOutOfRange { .. } => "PriceOutOfRange",
+            ValidationError::NoRuleForType { .. } => "NoRuleForType",
+            ValidationError::StopPriceRequired { .. } => "StopPriceRequired",
+            ValidationError::StopPriceNotAllowed { .. } => "StopPriceNotAllowed",
+            ValidationError::InvalidTrail { .. } => "InvalidTrail",
+            ValidationError::TrailNotAllowed { .. } => "TrailNotAllowed",
+            ValidationError::MissingExpireTime => "MissingExpireTime",
+            ValidationError::UnexpectedExpireTime { .. } => "UnexpectedExpireTime",
+            ValidationError::UnsupportedTimeInForce { .. } => "UnsupportedTimeInForce",
+            ValidationError::InstrumentExpired { .. } => "InstrumentExpired",
+            ValidationError::ExpireTimeInPast { .. } => "ExpireTimeInPast",
+            ValidationError::OrderTooOld { .. } => "OrderTooOld",
+            ValidationError::PriceCollarBreached { .. } => "PriceCollarBreached",
+            ValidationError::MissingReferencePrice { .. } => "MissingReferencePrice",
+            ValidationError::AdvExceeded { .. } => "AdvExceeded",
+            ValidationError::OutsideTradingHours { .. } => "OutsideTradingHours",
+            ValidationError::RestrictedSymbol { .. } => "RestrictedSymbol",
+            ValidationError::WatchListSymbol { .. } => "WatchListSymbol",
+            ValidationError::HardToBorrow { .. } => "HardToBorrow",
+            ValidationError::LocateRequired { .. } => "LocateRequired",
+            ValidationError::SelfTrade { .. } => "SelfTrade",
+            ValidationError::Throttled { .. } => "Throttled",
+            ValidationError::DuplicateOrder { .. } => "DuplicateOrder",
+            ValidationError::CustomCheck { .. } => "CustomCheck",
+            ValidationError::NearBandEdge { .. } => "NearBandEdge",
+        }
+    }
+
+    /// Offending values exposed as attributes on the Python exception.
+    pub fn attributes(&self, py: Python) -> Vec<(&'static str, PyObject)> {
+        match self {
+            ValidationError::UnknownSymbol { symbol } => vec![("symbol", symbol.into_py(py))],
+            ValidationError::NonPositiveQuantity { quantity, side } =>
+                vec![("quantity", quantity.into_py(py)), ("side", side.into_py(py))],
+            ValidationError::LotSize { symbol, quantity, lot_size } =>
+                vec![("symbol", symbol.into_py(py)), ("quantity", quantity.into_py(py)), ("lot_size", lot_size.into_py(py))],
+            ValidationError::TickSize { symbol, price, tick_size } =>
+                vec![("symbol", symbol.into_py(py)), ("price", price.into_py(py)), ("tick_size", tick_size.into_py(py))],
+            ValidationError::QuantityOutOfRange { quantity, bound, limit, layer, rule } =>
+                vec![("quantity", quantity.into_py(py)), ("bound", bound.into_py(py)), ("limit", limit.into_py(py)),
+                     ("layer", layer.into_py(py)), ("rule", rule.into_py(py))],
+            ValidationError::PriceOutOfRange { field, price, bound, limit, layer, rule } =>
+                vec![("field", field.into_py(py)), ("price", price.into_py(py)), ("bound", bound.into_py(py)),
+                     ("limit", limit.into_py(py)), ("layer", layer.into_py(py)), ("rule", rule.into_py(py))],
+            ValidationError::NoRuleForType { order_type }
+            | ValidationError::StopPriceRequired { order_type }
+            | ValidationError::TrailNotAllowed { order_type } => vec![("order_type", order_type.into_py(py))],
+            ValidationError::StopPriceNotAllowed { order_type, stop_price } =>
+                vec![("order_type", order_type.into_py(py)), ("stop_price", stop_price.into_py(py))],
+            ValidationError::InvalidTrail { order_type, trail_amount, trail_percent } =>
+                vec![("order_type", order_type.into_py(py)), ("trail_amount", trail_amount.into_py(py)),
+                     ("trail_percent", trail_percent.into_py(py))],
+            ValidationError::MissingExpireTime => Vec::new(),
+            ValidationError::UnexpectedExpireTime { time_in_force, expire_time } =>
+                vec![("time_in_force", time_in_force.into_py(py)), ("expire_time", expire_time.into_py(py))],
+            ValidationError::UnsupportedTimeInForce { time_in_force, order_type } =>
+                vec![("time_in_force", time_in_force.into_py(py)), ("order_type", order_type.into_py(py))],
+            ValidationError::InstrumentExpired { symbol, expiry } =>
+                vec![("symbol", symbol.into_py(py)), ("expiry", expiry.into_py(py))],
+            ValidationError::ExpireTimeInPast { expire_time } => vec![("expire_time", expire_time.into_py(py))],
+            ValidationError::OrderTooOld { age, max_age } =>
+                vec![("age", age.into_py(py)), ("max_age", max_age.into_py(py))],
+            ValidationError::PriceCollarBreached { symbol, price, reference, reference_price, deviation_percent, deviation_ticks } =>
+                vec![("symbol", symbol.into_py(py)), ("price", price.into_py(py)), ("reference", reference.into_py(py)),
+                     ("reference_price", reference_price.into_py(py)), ("deviation_percent", deviation_percent.into_py(py)),
+                     ("deviation_ticks", deviation_ticks.into_py(py))],
+            ValidationError::MissingReferencePrice { symbol, reference } =>
+                vec![("symbol", symbol.into_py(py)), ("reference", reference.into_py(py))],
+            ValidationError::AdvExceeded { symbol, notional, adv_notional, max_multiple } =>
+                vec![("symbol", symbol.into_py(py)), ("notional", notional.into_py(py)), ("adv_notional", adv_notional.into_py(py)),
+                     ("max_multiple", max_multiple.into_py(py))],
+            ValidationError::OutsideTradingHours { symbol, exchange, session, extended_hours } =>
+                vec![("symbol", symbol.into_py(py)), ("exchange", exchange.into_py(py)), ("session", session.into_py(py)),
+                     ("extended_hours", extended_hours.into_py(py))],
+            ValidationError::RestrictedSymbol { symbol, reason } | ValidationError::WatchListSymbol { symbol, reason } =>
+                vec![("symbol", symbol.into_py(py)), ("reason", reason.clone().into_py(py))],
+            ValidationError::HardToBorrow { symbol, short_quantity } =>
+                vec![("symbol", symbol.into_py(py)), ("short_quantity", short_quantity.into_py(py))],
+            ValidationError::LocateRequired { symbol, short_quantity, available } =>
+                vec![("symbol", symbol.into_py(py)), ("short_quantity", short_quantity.into_py(py)), ("available", available.into_py(py))],
+            ValidationError::SelfTrade { symbol, resting_order_id } =>
+                vec![("symbol", symbol.into_py(py)), ("resting_order_id", resting_order_id.into_py(py))],
+            ValidationError::Throttled { scope, key, per_second } =>
+                vec![("scope", scope.into_py(py)), ("key", key.into_py(py)), ("per_second", per_second.into_py(py))],
+            ValidationError::DuplicateOrder { symbol, previous_order_id, window } =>
+                vec![("symbol", symbol.into_py(py)), ("previous_order_id", previous_order_id.into_py(py)), ("window", window.into_py(py))],
+            ValidationError::CustomCheck { check, message } =>
+                vec![("check", check.into_py(py)), ("message", message.into_py(py))],
+            ValidationError::NearBandEdge { field, value, bound, limit, layer, rule } =>
+                vec![("field", field.into_py(py)), ("value", value.into_py(py)), ("bound", bound.into_py(py)),
+                     ("limit", limit.into_py(py)), ("layer", layer.into_py(py)), ("rule", rule.into_py(py))],
+        }
+    }
+}
+
+impl From<ValidationError> for PyErr {
+    fn from(err: ValidationError) -> PyErr {
+        let msg = err.to_string();
+        let py_err = match err {
+            ValidationError::UnknownSymbol { .. } => UnknownSymbolError::new_err(msg),
+            ValidationError::NonPositiveQuantity { .. } => NonPositiveQuantityError::new_err(msg),
+            ValidationError::LotSize { .. } => LotSizeError::new_err(msg),
+            ValidationError::TickSize { .. } => TickSizeError::new_err(msg),
+            ValidationError::QuantityOutOfRange { .. } => QuantityOutOfRangeError::new_err(msg),
+            ValidationError::PriceOutOfRange { .. } => PriceOutOfRangeError::new_err(msg),
+            ValidationError::NoRuleForType { .. } => NoRuleForTypeError::new_err(msg),
+            ValidationError::StopPriceRequired { .. } => StopPriceRequiredError::new_err(msg),
+            ValidationError::StopPriceNotAllowed { .. } => StopPriceNotAllowedError::new_err(msg),
+            ValidationError::InvalidTrail { .. } => InvalidTrailError::new_err(msg),
+            ValidationError::TrailNotAllowed { .. } => TrailNotAllowedError::new_err(msg),
+            ValidationError::MissingExpireTime => MissingExpireTimeError::new_err(msg),
+            ValidationError::UnexpectedExpireTime { .. } => UnexpectedExpireTimeError::new_err(msg),
+            ValidationError::UnsupportedTimeInForce { .. } => UnsupportedTimeInForceError::new_err(msg),
+            ValidationError::InstrumentExpired { .. } => InstrumentExpiredError::new_err(msg),
+            ValidationError::ExpireTimeInPast { .. } => ExpireTimeInPastError::new_err(msg),
+            ValidationError::OrderTooOld { .. } => OrderTooOldError::new_err(msg),
+            ValidationError::PriceCollarBreached { .. }
+            | ValidationError::MissingReferencePrice { .. } => PriceCollarError::new_err(msg),
+            ValidationError::AdvExceeded { .. } => AdvExceededError::new_err(msg),
+            ValidationError::OutsideTradingHours { .. } => OutsideTradingHoursError::new_err(msg),
+            ValidationError::RestrictedSymbol { .. } => RestrictedSymbolError::new_err(msg),
+            ValidationError::LocateRequired { .. } => LocateRequiredError::new_err(msg),
+            ValidationError::SelfTrade { .. } => SelfTradeError::new_err(msg),
+            ValidationError::Throttled { .. } => ThrottledError::new_err(msg),
+            ValidationError::DuplicateOrder { .. } => DuplicateOrderError::new_err(msg),
+            ValidationError::CustomCheck { .. } => CustomCheckError::new_err(msg),
+            ValidationError::WatchListSymbol { .. }
+            | ValidationError::HardToBorrow { .. }
+            | ValidationError::NearBandEdge { .. } => OrderValidationError::new_err(msg),
+        };
+        Python::with_gil(|py| {
+            let value = py_err.value(py);
+            for (name, attribute) in err.attributes(py) {
+                // Setting attributes on a fresh exception instance cannot fail
+                let _ = value.setattr(name, attribute);
+            }
+        });
+        py_err
+    }
+}
+
+pub fn register(py: Python, m: &PyModule) -> PyResult<()> {
+    m.add("OrderValidationError", py.get_type::<OrderValidationError>())?;
+    m.add("UnknownSymbolError", py.get_type::<UnknownSymbolError>())?;
+    m.add("NonPositiveQuantityError", py.get_type::<NonPositiveQuantityError>())?;
+    m.add("LotSizeError", py.get_type::<LotSizeError>())?;
+    m.add("TickSizeError", py.get_type::<TickSizeError>())?;
+    m.add("QuantityOutOfRangeError", py.get_type::<QuantityOutOfRangeError>())?;
+    m.add("PriceOutOfRangeError", py.get_type::<PriceOutOfRangeError>())?;
+    m.add("NoRuleForTypeError", py.get_type::<NoRuleForTypeError>())?;
+    m.add("StopPriceRequiredError", py.get_type::<StopPriceRequiredError>())?;
+    m.add("StopPriceNotAllowedError", py.get_type::<StopPriceNotAllowedError>())?;
+    m.add("InvalidTrailError", py.get_type::<InvalidTrailError>())?;
+    m.add("TrailNotAllowedError", py.get_type::<TrailNotAllowedError>())?;
+    m.add("MissingExpireTimeError", py.get_type::<MissingExpireTimeError>())?;
+    m.add("UnexpectedExpireTimeError", py.get_type::<UnexpectedExpireTimeError>())?;
+    m.add("UnsupportedTimeInForceError", py.get_type::<UnsupportedTimeInForceError>())?;
+    m.add("InstrumentExpiredError", py.get_type::<InstrumentExpiredError>())?;
+    m.add("ExpireTimeInPastError", py.get_type::<ExpireTimeInPastError>())?;
+    m.add("OrderTooOldError", py.get_type::<OrderTooOldError>())?;
+    m.add("PriceCollarError", py.get_type::<PriceCollarError>())?;
+    m.add("AdvExceededError", py.get_type::<AdvExceededError>())?;
+    m.add("OutsideTradingHoursError", py.get_type::<OutsideTradingHoursError>())?;
+    m.add("RestrictedSymbolError", py.get_type::<RestrictedSymbolError>())?;
+    m.add("LocateRequiredError", py.get_type::<LocateRequiredError>())?;
+    m.add("SelfTradeError", py.get_type::<SelfTradeError>())?;
+    m.add("ThrottledError", py.get_type::<ThrottledError>())?;
+    m.add("DuplicateOrderError", py.get_type::<DuplicateOrderError>())?;
+    m.add("CustomCheckError", py.get_type::<CustomCheckError>())?;
+    Ok(())
+}
@@ -1 +1,4 @@
-pub mod order_validator;
\ No newline at end of file
+pub mod order_validator;
+pub mod error;
+pub mod report;
+pub mod check;
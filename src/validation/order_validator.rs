@@ -1,28 +1,115 @@
 use pyo3::prelude::*;
 use pyo3::types::PyList;
 use std::collections::HashMap;
-use std::time::{SystemTime, UNIX_EPOCH};
+use std::fmt;
+use std::sync::Arc;
 use log::{info, warn, error};
-use crate::models::order::{Order, OrderType};
+use crate::models::order::{Order, OrderType, TimeInForce};
+use crate::models::numeric::{extract_limit, is_multiple_of, round_to_increment, Decimal, RoundingStrategy};
+use crate::models::instrument::{Instrument, InstrumentRegistry};
+use crate::models::market_data::{MarketDataStore, ReferencePrice};
+use crate::calendar::trading_calendar::{TradingCalendar, TradingSession};
+use crate::calendar::clock::ClockHandle;
+use crate::validation::error::ValidationError;
+use crate::validation::report::{Severity, ValidationReport};
+use crate::validation::check::{CheckRegistry, PythonCheck, ValidationCheck};
 
+/// Where a validation rule applies, from least to most specific. A bound set
+/// on a more specific layer overrides the same bound on the layers below it.
 #[pyclass]
-#[derive(Clone)]
+#[derive(Debug, Clone, Copy, Eq, PartialEq, Hash, PartialOrd, Ord)]
+pub enum RuleLayer {
+    Global,
+    OrderType,
+    Instrument,
+    Account,
+}
+
+impl fmt::Display for RuleLayer {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            RuleLayer::Global => write!(f, "global"),
+            RuleLayer::OrderType => write!(f, "order type"),
+            RuleLayer::Instrument => write!(f, "instrument"),
+            RuleLayer::Account => write!(f, "account"),
+        }
+    }
+}
+
+/// Quantity and price bands. Bounds left unset are inherited from the less
+/// specific layers.
+#[pyclass]
+#[derive(Clone, Default)]
 pub struct ValidationRule {
-    min_quantity: f64,
-    max_quantity: f64,
-    min_price: f64,
-    max_price: f64,
+    min_quantity: Option<Decimal>,
+    max_quantity: Option<Decimal>,
+    min_price: Option<Decimal>,
+    max_price: Option<Decimal>,
 }
 
 #[pymethods]
 impl ValidationRule {
     #[new]
-    pub fn new(min_quantity: f64, max_quantity: f64, min_price: f64, max_price: f64) -> Self {
-        ValidationRule {
-            min_quantity,
-            max_quantity,
-            min_price,
-            max_price,
+    #[pyo3(signature = (min_quantity=None, max_quantity=None, min_price=None, max_price=None))]
+    pub fn new(min_quantity: Option<&PyAny>, max_quantity: Option<&PyAny>, min_price: Option<&PyAny>, max_price: Option<&PyAny>) -> PyResult<Self> {
+        Ok(ValidationRule {
+            min_quantity: min_quantity.map(extract_limit).transpose()?,
+            max_quantity: max_quantity.map(extract_limit).transpose()?,
+            min_price: min_price.map(extract_limit).transpose()?,
+            max_price: max_price.map(extract_limit).transpose()?,
+        })
+    }
+}
+
+/// Dynamic price band around a reference price. An order breaches the collar
+/// when it is further away than either limit that is set.
+#[pyclass]
+#[derive(Debug, Clone)]
+pub struct PriceCollar {
+    #[pyo3(get)]
+    pub reference: ReferencePrice,
+    #[pyo3(get)]
+    pub max_percent: Option<Decimal>,
+    #[pyo3(get)]
+    pub max_ticks: Option<Decimal>,
+}
+
+#[pymethods]
+impl PriceCollar {
+    #[new]
+    #[pyo3(signature = (reference=ReferencePrice::Last, max_percent=None, max_ticks=None))]
+    pub fn new(reference: ReferencePrice, max_percent: Option<Decimal>, max_ticks: Option<Decimal>) -> PyResult<Self> {
+        if max_percent.is_none() && max_ticks.is_none() {
+            let err_msg = "Price collar needs a max_percent, a max_ticks or both";
+            error!("{}", err_msg);
+            return Err(PyErr::new::<pyo3::exceptions::PyValueError, _>(err_msg));
+        }
+        Ok(PriceCollar { reference, max_percent, max_ticks })
+    }
+}
+
+/// A bound together with the layer that set it.
+type Bound = Option<(Decimal, RuleLayer)>;
+
+/// The effective bands for one order after stacking every applicable layer.
+struct ResolvedRule {
+    min_quantity: Bound,
+    max_quantity: Bound,
+    min_price: Bound,
+    max_price: Bound,
+}
+
+impl ResolvedRule {
+    /// `layers` must be ordered from most to least specific.
+    fn resolve(layers: &[(RuleLayer, &ValidationRule)]) -> Self {
+        let pick = |field: fn(&ValidationRule) -> Option<Decimal>| {
+            layers.iter().find_map(|(layer, rule)| field(rule).map(|value| (value, *layer)))
+        };
+        ResolvedRule {
+            min_quantity: pick(|r| r.min_quantity),
+            max_quantity: pick(|r| r.max_quantity),
+            min_price: pick(|r| r.min_price),
+            max_price: pick(|r| r.max_price),
         }
     }
 }
@@ -30,9 +117,298 @@ impl ValidationRule {
 #[pyclass]
 #[derive(Clone)]
 pub struct OrderValidator {
+    global_rule: Option<ValidationRule>,
     rules: HashMap<OrderType, ValidationRule>,
-    symbols: Vec<String>,
+    instrument_rules: HashMap<String, ValidationRule>,
+    account_rules: HashMap<String, ValidationRule>,
+    instruments: InstrumentRegistry,
     max_order_age: Option<f64>,
+    auto_round: bool,
+    band_warning_threshold: Option<Decimal>,
+    checks: CheckRegistry,
+    market_data: MarketDataStore,
+    collar: Option<PriceCollar>,
+    instrument_collars: HashMap<String, PriceCollar>,
+    max_adv_multiple: Option<Decimal>,
+    calendar: Option<TradingCalendar>,
+    market_hours_severity: Severity,
+    missing_reference_severity: Severity,
+    clock: ClockHandle,
+}
+
+impl OrderValidator {
+    pub fn add_rust_check(&mut self, check: Arc<dyn ValidationCheck>, priority: i32) {
+        self.checks.add(check, priority);
+    }
+
+    /// Rules that apply to the order, most specific first.
+    fn rule_layers(&self, order: &Order) -> Vec<(RuleLayer, &ValidationRule)> {
+        let account_rule = order.account.as_ref().and_then(|account| self.account_rules.get(account));
+        [
+            (RuleLayer::Account, account_rule),
+            (RuleLayer::Instrument, self.instrument_rules.get(&order.symbol)),
+            (RuleLayer::OrderType, self.rules.get(&order.order_type)),
+            (RuleLayer::Global, self.global_rule.as_ref()),
+        ]
+        .into_iter()
+        .filter_map(|(layer, rule)| rule.map(|rule| (layer, rule)))
+        .collect()
+    }
+
+    fn describe_layer(&self, layer: RuleLayer, order: &Order) -> String {
+        match layer {
+            RuleLayer::Global => "global rule".to_string(),
+            RuleLayer::OrderType => format!("{} order type rule", order.order_type),
+            RuleLayer::Instrument => format!("instrument rule for {}", order.symbol),
+            RuleLayer::Account => format!("account rule for {}", order.account.as_deref().unwrap_or_default()),
+        }
+    }
+
+    fn check_band(&self, order: &Order, field: &'static str, value: Decimal, (min, max): (Bound, Bound),
+                  errors: &mut Vec<ValidationError>, warnings: &mut Vec<ValidationError>) {
+        let violation = match (min, max) {
+            (Some((min, layer)), _) if value < min => Some(("minimum", min, layer)),
+            (_, Some((max, layer))) if value > max => Some(("maximum", max, layer)),
+            _ => None,
+        };
+        match violation {
+            Some((bound, limit, layer)) if field == "quantity" => errors.push(ValidationError::QuantityOutOfRange {
+                quantity: value, bound, limit, layer, rule: self.describe_layer(layer, order),
+            }),
+            Some((bound, limit, layer)) => errors.push(ValidationError::PriceOutOfRange {
+                field, price: value, bound, limit, layer, rule: self.describe_layer(layer, order),
+            }),
+            None => {
+                // Inside the band, but close enough to an edge to be worth a look
+                if let Some(threshold) = self.band_warning_threshold {
+                    let margin = |bound: Decimal| bound.abs().checked_mul(threshold).unwrap_or(Decimal::MAX);
+                    let near_min = min.filter(|(min, _)| value < min.saturating_add(margin(*min))).map(|(min, layer)| ("minimum", min, layer));
+                    let near_max = max.filter(|(max, _)| value > max.saturating_sub(margin(*max))).map(|(max, layer)| ("maximum", max, layer));
+                    if let Some((bound, limit, layer)) = near_min.or(near_max) {
+                        warnings.push(ValidationError::NearBandEdge {
+                            field, value, bound, limit, layer, rule: self.describe_layer(layer, order),
+                        });
+                    }
+                }
+            },
+        }
+    }
+
+    /// Runs the checks against the order. Returns the errors in the order the
+    /// checks ran, followed by the warnings. Unless `collect_all` is set, the
+    /// custom checks are skipped once a built-in check has failed.
+    fn run_checks(&self, order: &Order, collect_all: bool) -> (Vec<ValidationError>, Vec<ValidationError>) {
+        let mut errors = Vec::new();
+        let mut warnings = Vec::new();
+
+        // An order that can't be snapped onto its grid is checked as it stands
+        let conformed;
+        let order = match self.auto_round.then(|| self.conform(order)) {
+            Some(Ok(order)) => {
+                conformed = order;
+                &conformed
+            },
+            _ => order,
+        };
+
+        // Validate symbol; nothing else can be checked without the instrument
+        let instrument = match self.instruments.get(&order.symbol) {
+            Some(instrument) => instrument,
+            None => {
+                errors.push(ValidationError::UnknownSymbol { symbol: order.symbol.clone() });
+                return (errors, warnings);
+            },
+        };
+
+        // Direction is carried by the side, so the quantity itself must be positive
+        if order.quantity <= Decimal::ZERO {
+            errors.push(ValidationError::NonPositiveQuantity { quantity: order.quantity, side: order.side });
+        }
+
+        // Validate tick and lot conformance against the instrument
+        if !is_multiple_of(order.quantity, instrument.lot_size) {
+            errors.push(ValidationError::LotSize { symbol: order.symbol.clone(), quantity: order.quantity, lot_size: instrument.lot_size });
+        }
+
+        let priced = order.limit_price.into_iter().chain(order.stop_price);
+        for price in priced {
+            let tick_size = instrument.tick_for(price);
+            if !is_multiple_of(price, tick_size) {
+                errors.push(ValidationError::TickSize { symbol: order.symbol.clone(), price, tick_size });
+            }
+        }
+
+        // Validate against the stacked global, order type, instrument and account rules
+        let layers = self.rule_layers(order);
+        if layers.is_empty() {
+            errors.push(ValidationError::NoRuleForType { order_type: order.order_type });
+        } else {
+            let rule = ResolvedRule::resolve(&layers);
+            self.check_band(order, "quantity", order.quantity, (rule.min_quantity, rule.max_quantity), &mut errors, &mut warnings);
+            if order.order_type != OrderType::Market {
+                self.check_band(order, "price", order.price, (rule.min_price, rule.max_price), &mut errors, &mut warnings);
+            }
+            if let Some(stop_price) = order.stop_price {
+                self.check_band(order, "stop price", stop_price, (rule.min_price, rule.max_price), &mut errors, &mut warnings);
+            }
+        }
+
+        // Validate stop and trailing parameters against the order type
+        match (order.order_type, order.stop_price) {
+            (OrderType::Stop | OrderType::StopLimit, None) => {
+                errors.push(ValidationError::StopPriceRequired { order_type: order.order_type });
+            },
+            (OrderType::Market | OrderType::Limit, Some(stop_price)) => {
+                errors.push(ValidationError::StopPriceNotAllowed { order_type: order.order_type, stop_price });
+            },
+            _ => {},
+        }
+
+        if order.order_type.is_trailing() {
+            let valid_trail = match (order.trail_amount, order.trail_percent) {
+                (Some(amount), None) => amount > Decimal::ZERO,
+                (None, Some(percent)) => percent > Decimal::ZERO && percent < Decimal::ONE_HUNDRED,
+                _ => false,
+            };
+            if !valid_trail {
+                errors.push(ValidationError::InvalidTrail {
+                    order_type: order.order_type, trail_amount: order.trail_amount, trail_percent: order.trail_percent,
+                });
+            }
+        } else if order.trail_amount.is_some() || order.trail_percent.is_some() {
+            errors.push(ValidationError::TrailNotAllowed { order_type: order.order_type });
+        }
+
+        // Validate time in force against the order type
+        match (order.time_in_force, order.expire_time) {
+            (TimeInForce::Gtd, None) => errors.push(ValidationError::MissingExpireTime),
+            (time_in_force, Some(expire_time)) if time_in_force != TimeInForce::Gtd => {
+                errors.push(ValidationError::UnexpectedExpireTime { time_in_force, expire_time });
+            },
+            _ => {},
+        }
+
+        let unsupported = match order.time_in_force {
+            // Stop orders rest until triggered, so they cannot be immediate
+            TimeInForce::Ioc | TimeInForce::Fok => order.order_type.is_stop(),
+            // Auction orders are plain market or limit orders
+            TimeInForce::Opg | TimeInForce::Cls => !matches!(order.order_type, OrderType::Market | OrderType::Limit),
+            _ => false,
+        };
+        if unsupported {
+            errors.push(ValidationError::UnsupportedTimeInForce { time_in_force: order.time_in_force, order_type: order.order_type });
+        }
+
+        let current_time = self.clock.now();
+
+        if instrument.is_expired(current_time) {
+            errors.push(ValidationError::InstrumentExpired { symbol: instrument.symbol.clone(), expiry: instrument.expiry });
+        }
+
+        if let Some(expire_time) = order.expire_time {
+            if expire_time <= current_time {
+                errors.push(ValidationError::ExpireTimeInPast { expire_time });
+            }
+        }
+
+        // Validate order age if max_order_age is set
+        if let Some(max_age) = self.max_order_age {
+            let age = current_time - order.timestamp;
+            if age > max_age {
+                errors.push(ValidationError::OrderTooOld { age, max_age });
+            }
+        }
+
+        // Validate the exchange session, allowing pre- and post-market only for extended hours orders
+        if let Some(exchange_calendar) = self.calendar.as_ref().and_then(|calendar| calendar.get(&instrument.exchange)) {
+            let session = exchange_calendar.session_at(current_time);
+            let allowed = match session {
+                TradingSession::Regular => true,
+                TradingSession::PreMarket | TradingSession::PostMarket => order.extended_hours,
+                TradingSession::Closed => false,
+            };
+            if !allowed {
+                let violation = ValidationError::OutsideTradingHours {
+                    symbol: order.symbol.clone(), exchange: instrument.exchange.clone(), session, extended_hours: order.extended_hours,
+                };
+                match self.market_hours_severity {
+                    Severity::Error => errors.push(violation),
+                    Severity::Warning => warnings.push(violation),
+                }
+            }
+        }
+
+        // Validate against live market data
+        let market = self.market_data.get(&order.symbol).unwrap_or_default();
+        let collar = self.instrument_collars.get(&order.symbol).or(self.collar.as_ref());
+        if let (Some(collar), Some(price)) = (collar, order.limit_price) {
+            match market.reference(collar.reference) {
+                Some(reference_price) if reference_price > Decimal::ZERO => {
+                    // A deviation too large to represent is outside any collar
+                    let distance = price.saturating_sub(reference_price).abs();
+                    let deviation_percent = distance.checked_div(reference_price)
+                        .and_then(|share| share.checked_mul(Decimal::ONE_HUNDRED))
+                        .unwrap_or(Decimal::MAX);
+                    // Without a tick size there are no ticks to count
+                    let tick_size = instrument.tick_for(reference_price);
+                    let deviation_ticks = (!tick_size.is_zero()).then(|| distance.checked_div(tick_size).unwrap_or(Decimal::MAX));
+                    let breached_ticks = collar.max_ticks.zip(deviation_ticks).is_some_and(|(max, ticks)| ticks > max);
+                    if collar.max_percent.is_some_and(|max| deviation_percent > max) || breached_ticks {
+                        let deviation_ticks = deviation_ticks.unwrap_or_default();
+                        errors.push(ValidationError::PriceCollarBreached {
+                            symbol: order.symbol.clone(), price, reference: collar.reference, reference_price,
+                            deviation_percent: deviation_percent.round_dp(4), deviation_ticks: deviation_ticks.round_dp(4),
+                        });
+                    }
+                },
+                // Without a reference the collar cannot be applied
+                _ => {
+                    let violation = ValidationError::MissingReferencePrice { symbol: order.symbol.clone(), reference: collar.reference };
+                    match self.missing_reference_severity {
+                        Severity::Error => errors.push(violation),
+                        Severity::Warning => warnings.push(violation),
+                    }
+                },
+            }
+        }
+
+        // Fat-finger check against the average daily traded value
+        if let Some(max_multiple) = self.max_adv_multiple {
+            let adv_price = market.previous_close.or(market.last);
+            if let (Some(adv), Some(adv_price)) = (market.average_daily_volume, adv_price) {
+                let notional = instrument.notional(order.quantity, order.limit_price.unwrap_or(adv_price));
+                let adv_notional = instrument.notional(adv, adv_price);
+                // A limit too large to represent is no effective limit
+                let max_notional = adv_notional.checked_mul(max_multiple).unwrap_or(Decimal::MAX);
+                if notional > max_notional {
+                    errors.push(ValidationError::AdvExceeded { symbol: order.symbol.clone(), notional, adv_notional, max_multiple });
+                }
+            }
+        }
+
+        if collect_all || errors.is_empty() {
+            for violation in self.checks.run(order, &instrument) {
+                match violation.severity {
+                    Severity::Error => errors.push(violation.into_error()),
+                    Severity::Warning => warnings.push(violation.into_error()),
+                }
+            }
+        }
+
+        (errors, warnings)
+    }
+
+    /// Returns the first failure, in the same order `validate_all` reports them.
+    pub fn check(&self, order: &Order) -> Result<(), ValidationError> {
+        let (errors, warnings) = self.run_checks(order, false);
+        for warning in &warnings {
+            warn!("Order {}: {}", order.order_id, warning);
+        }
+        match errors.into_iter().next() {
+            Some(err) => Err(err),
+            None => Ok(()),
+        }
+    }
 }
 
 #[pymethods]
@@ -40,72 +416,247 @@ impl OrderValidator {
     #[new]
     pub fn new() -> Self {
         OrderValidator {
+            global_rule: None,
             rules: HashMap::new(),
-            symbols: Vec::new(),
+            instrument_rules: HashMap::new(),
+            account_rules: HashMap::new(),
+            instruments: InstrumentRegistry::new(),
             max_order_age: None,
+            auto_round: false,
+            band_warning_threshold: None,
+            checks: CheckRegistry::default(),
+            market_data: MarketDataStore::new(),
+            collar: None,
+            instrument_collars: HashMap::new(),
+            max_adv_multiple: None,
+            calendar: None,
+            market_hours_severity: Severity::Error,
+            missing_reference_severity: Severity::Error,
+            clock: ClockHandle::default(),
         }
     }
 
     pub fn set_rule(&mut self, order_type: OrderType, rule: ValidationRule) -> PyResult<()> {
-        let order_type_clone = order_type.clone();
         self.rules.insert(order_type, rule);
-        info!("Set new rule for order type: {:?}", order_type_clone);
+        info!("Set new rule for order type: {:?}", order_type);
         Ok(())
     }
 
+    pub fn set_global_rule(&mut self, rule: ValidationRule) -> PyResult<()> {
+        self.global_rule = Some(rule);
+        info!("Set new global rule");
+        Ok(())
+    }
+
+    pub fn set_instrument_rule(&mut self, symbol: String, rule: ValidationRule) -> PyResult<()> {
+        info!("Set new rule for instrument: {}", symbol);
+        self.instrument_rules.insert(symbol, rule);
+        Ok(())
+    }
+
+    pub fn set_account_rule(&mut self, account: String, rule: ValidationRule) -> PyResult<()> {
+        info!("Set new rule for account: {}", account);
+        self.account_rules.insert(account, rule);
+        Ok(())
+    }
+
+    /// Registers a bare symbol as a plain equity with no tick or lot grid,
+    /// unless it is already known.
     pub fn add_symbol(&mut self, symbol: String) -> PyResult<()> {
-        self.symbols.push(symbol.clone());
+        if !self.instruments.contains(&symbol) {
+            self.instruments.add(Instrument::equity(symbol.clone()))?;
+        }
         info!("Added new symbol: {}", symbol);
         Ok(())
     }
 
+    pub fn add_instrument(&mut self, instrument: Instrument) -> PyResult<()> {
+        self.instruments.add(instrument)
+    }
+
+    /// Shares an existing registry, e.g. the one handed to the `RiskValidator`.
+    pub fn set_instruments(&mut self, instruments: InstrumentRegistry) -> PyResult<()> {
+        self.instruments = instruments;
+        Ok(())
+    }
+
+    pub fn get_instruments(&self) -> InstrumentRegistry {
+        self.instruments.clone()
+    }
+
     pub fn set_max_order_age(&mut self, max_age: Option<f64>) -> PyResult<()> {
         self.max_order_age = max_age;
         info!("Set max order age to: {:?}", max_age);
         Ok(())
     }
 
-    pub fn validate(&self, order: &Order) -> PyResult<()> {
-        info!("Validating order: {:?}", order);
+    /// Snap prices and quantities onto the instrument's grid instead of
+    /// rejecting orders that are off it.
+    pub fn set_auto_round(&mut self, auto_round: bool) -> PyResult<()> {
+        self.auto_round = auto_round;
+        info!("Set auto round to: {}", auto_round);
+        Ok(())
+    }
 
-        // Validate symbol
-        if !self.symbols.contains(&order.symbol) {
-            let err_msg = format!("Invalid symbol: {}", order.symbol);
+    /// Registers a Python callable `check(order, instrument)` to run after the
+    /// built-in checks. Lower priorities run first; ties run in registration
+    /// order. Registering a name again replaces the earlier check.
+    #[pyo3(signature = (name, check, priority=0))]
+    pub fn add_check(&mut self, name: String, check: PyObject, priority: i32) -> PyResult<()> {
+        self.add_rust_check(Arc::new(PythonCheck::new(name, check)), priority);
+        Ok(())
+    }
+
+    pub fn remove_check(&mut self, name: &str) -> bool {
+        let removed = self.checks.remove(name);
+        info!("Removed validation check {}: {}", name, removed);
+        removed
+    }
+
+    pub fn check_names(&self) -> Vec<String> {
+        self.checks.names()
+    }
+
+    /// Shares a market data store with the feed that keeps it up to date.
+    pub fn set_market_data(&mut self, market_data: MarketDataStore) -> PyResult<()> {
+        self.market_data = market_data;
+        Ok(())
+    }
+
+    pub fn get_market_data(&self) -> MarketDataStore {
+        self.market_data.clone()
+    }
+
+    /// Collar applied to limit prices of every symbol without its own collar.
+    pub fn set_collar(&mut self, collar: Option<PriceCollar>) -> PyResult<()> {
+        info!("Set price collar to: {:?}", collar);
+        self.collar = collar;
+        Ok(())
+    }
+
+    pub fn set_instrument_collar(&mut self, symbol: String, collar: Option<PriceCollar>) -> PyResult<()> {
+        info!("Set price collar for {} to: {:?}", symbol, collar);
+        match collar {
+            Some(collar) => self.instrument_collars.insert(symbol, collar),
+            None => self.instrument_collars.remove(&symbol),
+        };
+        Ok(())
+    }
+
+    /// Rejects orders worth more than this multiple of the symbol's average
+    /// daily traded value, e.g. 0.1 for a tenth of a day's volume.
+    pub fn set_max_adv_multiple(&mut self, max_multiple: Option<Decimal>) -> PyResult<()> {
+        self.max_adv_multiple = max_multiple;
+        info!("Set max ADV multiple to: {:?}", max_multiple);
+        Ok(())
+    }
+
+    /// Clock used for order age, expiry and market hours; the wall clock by default.
+    pub fn set_clock(&mut self, clock: ClockHandle) -> PyResult<()> {
+        self.clock = clock;
+        Ok(())
+    }
+
+    pub fn get_clock(&self) -> ClockHandle {
+        self.clock.clone()
+    }
+
+    /// Checks orders against the session of their instrument's exchange.
+    /// Exchanges without a calendar are not checked.
+    pub fn set_calendar(&mut self, calendar: Option<TradingCalendar>) -> PyResult<()> {
+        self.calendar = calendar;
+        Ok(())
+    }
+
+    /// Whether orders outside the allowed session are rejected (`Error`, the
+    /// default) or only flagged (`Warning`).
+    pub fn set_market_hours_severity(&mut self, severity: Severity) -> PyResult<()> {
+        self.market_hours_severity = severity;
+        info!("Set market hours severity to: {}", severity);
+        Ok(())
+    }
+
+    /// Whether orders that can't be collared for want of a reference price
+    /// are rejected (`Error`, the default) or only flagged (`Warning`).
+    pub fn set_missing_reference_severity(&mut self, severity: Severity) -> PyResult<()> {
+        self.missing_reference_severity = severity;
+        info!("Set missing reference price severity to: {}", severity);
+        Ok(())
+    }
+
+    /// Warn about values within this fraction of a band edge, e.g. 0.05 for
+    /// prices within 5% of the maximum. Warnings never reject an order.
+    pub fn set_band_warning_threshold(&mut self, threshold: Option<Decimal>) -> PyResult<()> {
+        if let Some(threshold) = threshold.filter(|t| *t < Decimal::ZERO || *t > Decimal::ONE) {
+            let err_msg = format!("Band warning threshold must be between 0 and 1, got {}", threshold);
             error!("{}", err_msg);
             return Err(PyErr::new::<pyo3::exceptions::PyValueError, _>(err_msg));
         }
+        self.band_warning_threshold = threshold;
+        info!("Set band warning threshold to: {:?}", threshold);
+        Ok(())
+    }
 
-        // Validate order type and rules
-        if let Some(rule) = self.rules.get(&order.order_type) {
-            if order.quantity < rule.min_quantity || order.quantity > rule.max_quantity {
-                let err_msg = format!("Order quantity must be between {} and {}", rule.min_quantity, rule.max_quantity);
-                error!("{}", err_msg);
-                return Err(PyErr::new::<pyo3::exceptions::PyValueError, _>(err_msg));
-            }
+    /// Returns a copy of the order snapped onto its instrument's tick and lot
+    /// grid when auto-round is on. Prices round in the order's favour so a
+    /// snapped order never trades at a worse price than requested, and
+    /// quantities round down to a whole lot.
+    pub fn conform(&self, order: &Order) -> PyResult<Order> {
+        let mut conformed = order.clone();
+        let instrument = match self.instruments.get(&order.symbol) {
+            Some(instrument) if self.auto_round => instrument,
+            _ => return Ok(conformed),
+        };
 
-            if order.order_type != OrderType::Market && (order.price < rule.min_price || order.price > rule.max_price) {
-                let err_msg = format!("Order price must be between {} and {}", rule.min_price, rule.max_price);
-                error!("{}", err_msg);
-                return Err(PyErr::new::<pyo3::exceptions::PyValueError, _>(err_msg));
-            }
+        let (limit_rounding, stop_rounding) = if order.side.is_buy() {
+            (RoundingStrategy::ToNegativeInfinity, RoundingStrategy::ToPositiveInfinity)
         } else {
-            let err_msg = format!("No validation rule for order type: {:?}", order.order_type);
-            error!("{}", err_msg);
-            return Err(PyErr::new::<pyo3::exceptions::PyValueError, _>(err_msg));
+            (RoundingStrategy::ToPositiveInfinity, RoundingStrategy::ToNegativeInfinity)
+        };
+        if let Some(limit_price) = order.limit_price {
+            conformed.set_price(round_to_increment(limit_price, instrument.tick_for(limit_price), limit_rounding)?);
         }
-
-        // Validate order age if max_order_age is set
-        if let Some(max_age) = self.max_order_age {
-            let current_time = SystemTime::now().duration_since(UNIX_EPOCH).unwrap().as_secs_f64();
-            if current_time - order.timestamp > max_age {
-                let err_msg = "Order is too old";
-                error!("{}", err_msg);
-                return Err(PyErr::new::<pyo3::exceptions::PyValueError, _>(err_msg));
+        if let Some(stop_price) = order.stop_price {
+            conformed.stop_price = Some(round_to_increment(stop_price, instrument.tick_for(stop_price), stop_rounding)?);
+            if order.order_type == OrderType::Stop {
+                conformed.set_price(conformed.stop_price.unwrap());
             }
         }
+        conformed.quantity = round_to_increment(order.quantity, instrument.lot_size, RoundingStrategy::ToZero)?;
 
-        info!("Order validation successful");
-        Ok(())
+        if conformed.price != order.price || conformed.stop_price != order.stop_price || conformed.quantity != order.quantity {
+            info!("Rounded order {} onto the grid of {}: quantity {} -> {}, price {} -> {}",
+                  order.order_id, instrument.symbol, order.quantity, conformed.quantity, order.price, conformed.price);
+        }
+        Ok(conformed)
+    }
+
+    pub fn validate(&self, order: &Order) -> PyResult<()> {
+        info!("Validating order: {:?}", order);
+        match self.check(order) {
+            Ok(()) => {
+                info!("Order validation successful");
+                Ok(())
+            },
+            Err(err) => {
+                error!("{}", err);
+                Err(err.into())
+            },
+        }
+    }
+
+    /// Runs every check instead of stopping at the first failure.
+    pub fn validate_all(&self, order: &Order) -> ValidationReport {
+        let (errors, warnings) = self.run_checks(order, true);
+        let report = ValidationReport::new(order.order_id, errors, warnings);
+        info!("Order {} validation report: {} errors, {} warnings", order.order_id, report.error_count(), report.warning_count());
+        report
+    }
+
+    pub fn validate_all_multiple(&self, orders: &PyList) -> PyResult<Vec<ValidationReport>> {
+        orders.iter()
+            .map(|order_obj| Ok(self.validate_all(&order_obj.extract()?)))
+            .collect()
     }
 
     pub fn validate_multiple(&self, orders: &PyList) -> PyResult<Vec<(usize, String)>> {
@@ -0,0 +1,132 @@
+use pyo3::prelude::*;
+use pyo3::types::PyDict;
+use std::fmt;
+use crate::validation::error::ValidationError;
+
+#[pyclass]
+#[derive(Debug, Clone, Copy, Eq, PartialEq, Hash)]
+pub enum Severity {
+    /// Recorded, but the order may still be sent
+    Warning,
+    /// The order must not be sent
+    Error,
+}
+
+impl fmt::Display for Severity {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Severity::Warning => write!(f, "WARNING"),
+            Severity::Error => write!(f, "ERROR"),
+        }
+    }
+}
+
+#[pyclass]
+#[derive(Debug, Clone)]
+pub struct Violation {
+    #[pyo3(get)]
+    pub severity: Severity,
+    error: ValidationError,
+}
+
+impl Violation {
+    pub fn new(severity: Severity, error: ValidationError) -> Self {
+        Violation { severity, error }
+    }
+
+    pub fn into_error(self) -> ValidationError {
+        self.error
+    }
+}
+
+#[pymethods]
+impl Violation {
+    #[getter]
+    pub fn code(&self) -> &'static str {
+        self.error.code()
+    }
+
+    #[getter]
+    pub fn message(&self) -> String {
+        self.error.to_string()
+    }
+
+    /// The offending values, keyed like the attributes on the matching exception.
+    #[getter]
+    pub fn details<'py>(&self, py: Python<'py>) -> PyResult<&'py PyDict> {
+        let details = PyDict::new(py);
+        for (name, value) in self.error.attributes(py) {
+            details.set_item(name, value)?;
+        }
+        Ok(details)
+    }
+
+    pub fn __str__(&self) -> String {
+        format!("{} {}: {}", self.severity, self.error.code(), self.error)
+    }
+}
+
+/// Every violation found on one order, not just the first.
+#[pyclass]
+#[derive(Debug, Clone)]
+pub struct ValidationReport {
+    #[pyo3(get)]
+    pub order_id: u64,
+    #[pyo3(get)]
+    pub violations: Vec<Violation>,
+}
+
+impl ValidationReport {
+    pub fn new(order_id: u64, errors: Vec<ValidationError>, warnings: Vec<ValidationError>) -> Self {
+        let errors = errors.into_iter().map(|error| Violation::new(Severity::Error, error));
+        let warnings = warnings.into_iter().map(|error| Violation::new(Severity::Warning, error));
+        ValidationReport {
+            order_id,
+            violations: errors.chain(warnings).collect(),
+        }
+    }
+
+    fn with_severity(&self, severity: Severity) -> Vec<Violation> {
+        self.violations.iter().filter(|v| v.severity == severity).cloned().collect()
+    }
+
+    /// First error, in the order the checks ran.
+    pub fn first_error(&self) -> Option<&ValidationError> {
+        self.violations.iter().find(|v| v.severity == Severity::Error).map(|v| &v.error)
+    }
+}
+
+#[pymethods]
+impl ValidationReport {
+    /// True when there are no errors; warnings do not block an order.
+    #[getter]
+    pub fn is_valid(&self) -> bool {
+        self.first_error().is_none()
+    }
+
+    pub fn errors(&self) -> Vec<Violation> {
+        self.with_severity(Severity::Error)
+    }
+
+    pub fn warnings(&self) -> Vec<Violation> {
+        self.with_severity(Severity::Warning)
+    }
+
+    pub fn error_count(&self) -> usize {
+        self.violations.iter().filter(|v| v.severity == Severity::Error).count()
+    }
+
+    pub fn warning_count(&self) -> usize {
+        self.violations.iter().filter(|v| v.severity == Severity::Warning).count()
+    }
+
+    pub fn __len__(&self) -> usize {
+        self.violations.len()
+    }
+
+    pub fn __str__(&self) -> String {
+        let lines: Vec<String> = self.violations.iter().map(|v| format!("  {}", v.__str__())).collect();
+        format!("ValidationReport(order_id={}, errors={}, warnings={})\n{}",
+                self.order_id, self.error_count(), self.warning_count(), lines.join("\n"))
+    }
+}
@@ -1,19 +1,31 @@
 import asyncio
 import pytest
-from quanta_engine import OrderValidator, ValidationRule, Order, OrderType
+from quanta_engine import OrderValidator, ValidationRule, Order, OrderType, ExecutionReport, ExecType
 from datetime import datetime
 from typing import Dict, Any, List
 from collections import namedtuple
 from abc import ABC, abstractmethod
 from quanta_engine import OrderExecutor as RustOrderExecutor, OrderValidator, Order
-from ib_insync import IB, MarketOrder, LimitOrder, StopOrder
+from ib_insync import IB, MarketOrder, LimitOrder, StopOrder, StopLimitOrder
+
+def ib_action(side: str) -> str:
+    # IB only distinguishes BUY and SELL; short sales are flagged on the account side
+    return 'BUY' if side in ('Buy', 'BuyToCover') else 'SELL'
 
 class BrokerAdapter(ABC):
     @abstractmethod
     async def place_order(self, order: Dict[str, Any]) -> Dict[str, Any]:
         pass
 
+def mock_order(name: str, fields: List[str], *values):
+    # As with IB orders, an orderId of 0 means the broker has not assigned one yet
+    return namedtuple(name, ['action', 'totalQuantity', *fields, 'orderId'], defaults=(0,))(*values)
+
 class MockIB:
+    def __init__(self):
+        self.next_order_id = 1
+        self.trades = {}
+
     def qualifyContracts(self, symbol):
         Contract = namedtuple('Contract', ['symbol'])
         return [Contract(symbol=symbol)]
@@ -26,77 +38,128 @@ class MockIB:
         # Simulate order placement
         await asyncio.sleep(0.1)  # Simulate network delay
 
-        order_id = hash(f"{contract.symbol}{order.action}{order.totalQuantity}")
-        status = "Filled"
-        filled = order.totalQuantity
-        remaining = 0
-        avg_fill_price = getattr(order, 'lmtPrice', 100.0)  # Use limit price if available, else use a dummy price
+        if order.orderId in self.trades:
+            # Placing an order again under its own orderId amends it
+            order_id = order.orderId
+            status = "Submitted"
+            filled = 0
+            remaining = order.totalQuantity
+            avg_fill_price = 0.0
+        else:
+            order_id = self.next_order_id
+            self.next_order_id += 1
+            status = "Filled"
+            filled = order.totalQuantity
+            remaining = 0
+            avg_fill_price = getattr(order, 'lmtPrice', 100.0)  # Use limit price if available, else use a dummy price
 
         order_status = OrderStatus(status=status, filled=filled, remaining=remaining, avgFillPrice=avg_fill_price)
-        order = Order(orderId=order_id)
-        return Trade(order=order, orderStatus=order_status)
+        trade = Trade(order=Order(orderId=order_id), orderStatus=order_status)
+        self.trades[order_id] = trade
+        return trade
+
+    async def cancelOrder(self, order):
+        await asyncio.sleep(0.1)  # Simulate network delay
+
+        trade = self.trades[order.orderId]
+        order_status = trade.orderStatus._replace(status="Cancelled", remaining=0)
+        trade = trade._replace(orderStatus=order_status)
+        self.trades[order.orderId] = trade
+        return trade
+
+def trade_result(trade) -> Dict[str, Any]:
+    return {
+        'order_id': trade.order.orderId,
+        'status': trade.orderStatus.status,
+        'filled': trade.orderStatus.filled,
+        'remaining': trade.orderStatus.remaining,
+        'avg_fill_price': trade.orderStatus.avgFillPrice,
+    }
 
 class MockIBAdapter(BrokerAdapter):
     def __init__(self):
         self.ib_client = MockIB()
+        # Broker trades by engine order_id, so cancels and replaces find their order
+        self.trades = {}
 
     async def place_order(self, order: Dict[str, Any]) -> Dict[str, Any]:
+        request_type = order.get('request_type', 'new')
+        if request_type == 'cancel':
+            trade = await self.ib_client.cancelOrder(self.trades[order['order_id']].order)
+            self.trades[order['order_id']] = trade
+            return trade_result(trade)
+
         symbol = order['symbol']
         quantity = order['quantity']
         order_type = order['order_type']
+        action = ib_action(order['side'])
 
         contract = self.ib_client.qualifyContracts(symbol)[0]
 
         if order_type == 'Market':
-            ib_order = namedtuple('MarketOrder', ['action', 'totalQuantity'])('BUY', quantity)
+            ib_order = mock_order('MarketOrder', [], action, quantity)
         elif order_type == 'Limit':
-            ib_order = namedtuple('LimitOrder', ['action', 'totalQuantity', 'lmtPrice'])('BUY', quantity, order['price'])
+            ib_order = mock_order('LimitOrder', ['lmtPrice'], action, quantity, order['limit_price'])
         elif order_type == 'Stop':
-            ib_order = namedtuple('StopOrder', ['action', 'totalQuantity', 'auxPrice'])('BUY', quantity, order['price'])
+            ib_order = mock_order('StopOrder', ['auxPrice'], action, quantity, order['stop_price'])
+        elif order_type == 'StopLimit':
+            ib_order = mock_order('StopLimitOrder', ['lmtPrice', 'auxPrice'], action, quantity, order['limit_price'], order['stop_price'])
         else:
             raise ValueError(f"Unsupported order type: {order_type}")
 
+        if request_type == 'replace':
+            ib_order = ib_order._replace(orderId=self.trades[order['order_id']].order.orderId)
+        elif request_type != 'new':
+            raise ValueError(f"Unsupported request type: {request_type}")
+
         trade = await self.ib_client.placeOrder(contract, ib_order)
+        self.trades[order['order_id']] = trade
         await asyncio.sleep(0.1)  # Simulate processing time
 
-        return {
-            'order_id': trade.order.orderId,
-            'status': trade.orderStatus.status,
-            'filled': trade.orderStatus.filled,
-            'remaining': trade.orderStatus.remaining,
-            'avg_fill_price': trade.orderStatus.avgFillPrice,
-        }
+        return trade_result(trade)
     
 class IBAdapter(BrokerAdapter):
     def __init__(self, ib_client: IB):
         self.ib_client = ib_client
+        # Broker trades by engine order_id, so cancels and replaces find their order
+        self.trades = {}
 
     async def place_order(self, order: Dict[str, Any]) -> Dict[str, Any]:
+        request_type = order.get('request_type', 'new')
+        if request_type == 'cancel':
+            trade = self.ib_client.cancelOrder(self.trades[order['order_id']].order)
+            await asyncio.sleep(1)  # Give IB a second to process the cancel
+            return trade_result(trade)
+
         symbol = order['symbol']
         quantity = order['quantity']
         order_type = order['order_type']
+        action = ib_action(order['side'])
 
         contract = self.ib_client.qualifyContracts(symbol)[0]
 
         if order_type == 'Market':
-            ib_order = MarketOrder('BUY', quantity)
+            ib_order = MarketOrder(action, quantity)
         elif order_type == 'Limit':
-            ib_order = LimitOrder('BUY', quantity, order['price'])
+            ib_order = LimitOrder(action, quantity, order['limit_price'])
         elif order_type == 'Stop':
-            ib_order = StopOrder('BUY', quantity, order['price'])
+            ib_order = StopOrder(action, quantity, order['stop_price'])
+        elif order_type == 'StopLimit':
+            ib_order = StopLimitOrder(action, quantity, order['limit_price'], order['stop_price'])
         else:
             raise ValueError(f"Unsupported order type: {order_type}")
 
+        if request_type == 'replace':
+            # IB amends an order that is placed again under its own orderId
+            ib_order.orderId = self.trades[order['order_id']].order.orderId
+        elif request_type != 'new':
+            raise ValueError(f"Unsupported request type: {request_type}")
+
         trade = self.ib_client.placeOrder(contract, ib_order)
+        self.trades[order['order_id']] = trade
         await asyncio.sleep(1)  # Give IB a second to process the order
 
-        return {
-            'order_id': trade.order.orderId,
-            'status': trade.orderStatus.status,
-            'filled': trade.orderStatus.filled,
-            'remaining': trade.orderStatus.remaining,
-            'avg_fill_price': trade.orderStatus.avgFillPrice,
-        }
+        return trade_result(trade)
 
 
 class OrderExecutor:
@@ -125,6 +188,16 @@ class OrderExecutor:
         results = await loop.run_in_executor(None, self.rust_executor.execute_queued)
         return await asyncio.gather(*results)
 
+    async def cancel(self, order_id: int):
+        loop = asyncio.get_event_loop()
+        result = await loop.run_in_executor(None, self.rust_executor.cancel, order_id)
+        return await result
+
+    async def replace(self, order_id: int, new_quantity, new_price):
+        loop = asyncio.get_event_loop()
+        result = await loop.run_in_executor(None, self.rust_executor.replace, order_id, new_quantity, new_price)
+        return await result
+
 @pytest.mark.asyncio
 async def test_order_executor():
     print("Running tests...")
@@ -196,6 +269,23 @@ async def test_order_executor():
     print("Invalid quantity order test passed successfully!")
     print("Result: ValueError raised as expected")
 
+    # Test 7: Replace and cancel reach the broker as their own requests
+    resting_order = Order("AAPL", 10, OrderType.Limit, 150.0, datetime.now())
+    result = await executor.execute(resting_order)
+    broker_order_id = result['order_id']
+    executor.rust_executor.apply_report(resting_order.order_id, ExecutionReport(ExecType.Accepted))
+    result = await executor.replace(resting_order.order_id, 20, 151.0)
+    assert result['order_id'] == broker_order_id
+    assert result['status'] == 'Submitted'
+    assert result['remaining'] == 20
+    executor.rust_executor.apply_report(resting_order.order_id, ExecutionReport(ExecType.Replaced))
+    result = await executor.cancel(resting_order.order_id)
+    assert result['order_id'] == broker_order_id
+    assert result['status'] == 'Cancelled'
+    assert result['remaining'] == 0
+    print("Replace and cancel passed successfully!")
+    print(f"Result: {result}")
+
     print("All tests passed successfully!")
 
 if __name__ == "__main__":
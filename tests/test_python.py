@@ -1,6 +1,18 @@
 import pytest
-from quanta_engine import Order, OrderType, RiskValidator, StrategyManager, StrategyStatus, OrderValidator, ValidationRule, User
+from quanta_engine import Order, OrderType, RiskValidator, StrategyManager, StrategyStatus, OrderValidator, ValidationRule, User, Side, TimeInForce
+from quanta_engine import OrderStateTracker, OrderStatus, ExecType, ExecutionReport, OrderExecutor, ContingencyType
+from quanta_engine import Instrument, InstrumentRegistry, AssetClass, OptionRight, TickTable
+from quanta_engine import RuleLayer, OrderValidationError, UnknownSymbolError, QuantityOutOfRangeError, PriceOutOfRangeError, NoRuleForTypeError, OrderTooOldError, LotSizeError, CustomCheckError
+from quanta_engine import PriceCollar, PriceCollarError, AdvExceededError, ReferencePrice, MarketData, MarketDataStore
+from quanta_engine import TradingCalendar, ExchangeCalendar, TradingSession, OutsideTradingHoursError
+from quanta_engine import Severity, Clock
+from quanta_engine import Position, PositionLedger, CostBasis, PnlEngine
+from quanta_engine import RiskLimit, RiskDecision, RiskLimitError, LimitScope, ScopeLimits, LimitBook
+from quanta_engine import OrderThrottle, ThrottleScope, ThrottledError, DuplicateOrderError
+from quanta_engine import SelfTradePolicy, SelfTradePrevention, SelfTradeError
+from quanta_engine import RestrictedList, RestrictionLevel, LocateInventory, ComplianceChecker, RestrictedSymbolError, LocateRequiredError
 from datetime import datetime, timedelta
+from zoneinfo import ZoneInfo
 
 class Strategy:
     '''
@@ -23,8 +35,9 @@ class Strategy:
         self.status = status
         self.user = user
 
-from quanta_engine import Order, OrderType, RiskValidator, StrategyManager, StrategyStatus, OrderValidator, ValidationRule, User
+from quanta_engine import Order, OrderType, RiskValidator, StrategyManager, StrategyStatus, OrderValidator, ValidationRule, User, Side
 from datetime import datetime, timedelta
+from decimal import Decimal
 import logging
 
 logging.basicConfig(level=logging.INFO)
@@ -92,6 +105,1361 @@ def test_risk_validation():
     is_valid = risk_validator.validate_order(order, current_position=500, daily_pnl=-1000)
     print(f"Order is valid: {is_valid}")
 
+def test_order_side():
+    # Direction is inferred from the sign of the quantity when no side is given
+    buy = Order("AAPL", 100, OrderType.Limit, 150.0, datetime.now())
+    sell = Order("AAPL", -100, OrderType.Limit, 150.0, datetime.now())
+    assert buy.side == Side.Buy
+    assert sell.side == Side.Sell
+    assert sell.quantity == 100
+    assert sell.signed_quantity() == -100
+
+    with pytest.raises(ValueError):
+        Order("AAPL", -100, OrderType.Limit, 150.0, datetime.now(), side=Side.Buy)
+
+    validator = OrderValidator()
+    validator.add_symbol("AAPL")
+    validator.set_rule(OrderType.Limit, ValidationRule(min_quantity=1, max_quantity=1000, min_price=0.01, max_price=10000))
+    validator.validate(sell)
+    sell.quantity = -100
+    with pytest.raises(ValueError):
+        validator.validate(sell)
+
+    risk_validator = RiskValidator(max_position_size=1000, max_daily_loss=5000, max_order_value=100000)
+    short = Order("AAPL", 100, OrderType.Limit, 150.0, datetime.now(), side=Side.SellShort)
+    cover = Order("AAPL", 100, OrderType.Limit, 150.0, datetime.now(), side=Side.BuyToCover)
+    long_sale = Order("AAPL", 100, OrderType.Limit, 150.0, datetime.now(), side=Side.Sell)
+    assert risk_validator.validate_order(long_sale, current_position=100, daily_pnl=0)
+    assert not risk_validator.validate_order(long_sale, current_position=50, daily_pnl=0)
+    assert risk_validator.validate_order(short, current_position=0, daily_pnl=0)
+    assert risk_validator.validate_order(cover, current_position=-100, daily_pnl=0)
+    assert not risk_validator.validate_order(cover, current_position=0, daily_pnl=0)
+
+def test_order_lifecycle():
+    tracker = OrderStateTracker()
+    order = Order("AAPL", 100, OrderType.Limit, 150.0, datetime.now())
+    tracker.track(order)
+    assert tracker.get_state(order.order_id).status == OrderStatus.New
+
+    assert tracker.apply_report(order.order_id, ExecutionReport(ExecType.PendingNew)) == OrderStatus.PendingNew
+    assert tracker.apply_report(order.order_id, ExecutionReport(ExecType.Accepted)) == OrderStatus.Accepted
+    assert tracker.apply_report(order.order_id, ExecutionReport(ExecType.Fill, 40, 150.0)) == OrderStatus.PartiallyFilled
+    assert tracker.apply_report(order.order_id, ExecutionReport(ExecType.Fill, 60, 151.0)) == OrderStatus.Filled
+
+    state = tracker.get_state(order.order_id)
+    assert state.filled_quantity == 100
+    assert state.avg_fill_price == Decimal("150.6")
+    assert state.leaves_quantity() == 0
+    assert tracker.open_orders() == []
+
+    # Fills too large to total saturate rather than panic
+    huge = Order("AAPL", 100, OrderType.Limit, 150.0, datetime.now())
+    tracker.track(huge)
+    tracker.apply_report(huge.order_id, ExecutionReport(ExecType.PendingNew))
+    tracker.apply_report(huge.order_id, ExecutionReport(ExecType.Fill, 50, Decimal("1e27")))
+    assert tracker.apply_report(huge.order_id, ExecutionReport(ExecType.Fill, 50, Decimal("1e27"))) == OrderStatus.Filled
+    assert tracker.get_state(huge.order_id).avg_fill_price > Decimal("1e26")
+
+    # Terminal states do not accept further reports
+    with pytest.raises(ValueError):
+        tracker.apply_report(order.order_id, ExecutionReport(ExecType.Cancelled))
+
+    # A rejected cancel restores the previous state
+    resting = Order("AAPL", 100, OrderType.Limit, 150.0, datetime.now())
+    tracker.track(resting)
+    tracker.apply_report(resting.order_id, ExecutionReport(ExecType.PendingNew))
+    tracker.apply_report(resting.order_id, ExecutionReport(ExecType.Accepted))
+    tracker.apply_report(resting.order_id, ExecutionReport(ExecType.PendingCancel))
+    assert tracker.apply_report(resting.order_id, ExecutionReport(ExecType.CancelRejected, reason="too late")) == OrderStatus.Accepted
+    with pytest.raises(ValueError):
+        tracker.apply_report(resting.order_id, ExecutionReport(ExecType.Fill, 150, 150.0))
+    assert len(tracker.open_orders()) == 1
+
+    # Repeated cancel requests still restore the status before the first one
+    tracker.apply_report(resting.order_id, ExecutionReport(ExecType.PendingCancel))
+    tracker.apply_report(resting.order_id, ExecutionReport(ExecType.PendingCancel))
+    assert tracker.apply_report(resting.order_id, ExecutionReport(ExecType.CancelRejected)) == OrderStatus.Accepted
+
+    # An unacknowledged order that traded while pending cancel is partially filled
+    unacked = Order("AAPL", 100, OrderType.Limit, 150.0, datetime.now())
+    tracker.track(unacked)
+    tracker.apply_report(unacked.order_id, ExecutionReport(ExecType.PendingNew))
+    tracker.apply_report(unacked.order_id, ExecutionReport(ExecType.PendingCancel))
+    tracker.apply_report(unacked.order_id, ExecutionReport(ExecType.Fill, 30, 150.0))
+    assert tracker.apply_report(unacked.order_id, ExecutionReport(ExecType.CancelRejected)) == OrderStatus.PartiallyFilled
+
+def make_executor(validator=None):
+    if validator is None:
+        validator = OrderValidator()
+        validator.add_symbol("AAPL")
+        validator.set_rule(OrderType.Market, ValidationRule(min_quantity=1, max_quantity=1000, min_price=0, max_price=float('inf')))
+        validator.set_rule(OrderType.Limit, ValidationRule(min_quantity=1, max_quantity=1000, min_price=0.01, max_price=10000))
+    sent = []
+    def callback(order_dict):
+        sent.append(order_dict)
+        return order_dict
+    return OrderExecutor(validator, callback), sent
+
+def test_order_ids_and_idempotency():
+    first = Order("AAPL", 100, OrderType.Limit, 150.0, datetime.now(), client_order_id="strat1-0001")
+    second = Order("AAPL", 100, OrderType.Limit, 150.0, datetime.now())
+    assert first.order_id != second.order_id
+    assert first.client_order_id == "strat1-0001"
+    assert second.client_order_id is None
+
+    executor, sent = make_executor()
+    executor.execute(first)
+    assert sent[-1]["order_id"] == first.order_id
+    assert sent[-1]["client_order_id"] == "strat1-0001"
+    assert executor.get_order_state(first.order_id).status == OrderStatus.PendingNew
+    assert executor.get_order_state_by_client_id("strat1-0001").order_id == first.order_id
+
+    # A retry with the same client order id is refused and never reaches the broker
+    retry = Order("AAPL", 100, OrderType.Limit, 150.0, datetime.now(), client_order_id="strat1-0001")
+    with pytest.raises(ValueError):
+        executor.execute(retry)
+    with pytest.raises(ValueError):
+        executor.queue_order(retry)
+    # So is re-sending the very same order object
+    with pytest.raises(ValueError):
+        executor.execute(first)
+    assert len(sent) == 1
+
+    executor.apply_report(first.order_id, ExecutionReport(ExecType.Accepted))
+    executor.apply_report(first.order_id, ExecutionReport(ExecType.Fill, 100, 150.0))
+    assert executor.get_order_state(first.order_id).status == OrderStatus.Filled
+    assert executor.open_orders() == []
+
+def test_cancel_and_replace():
+    risk_validator = RiskValidator(max_position_size=1000, max_daily_loss=5000, max_order_value=100000)
+    executor, sent = make_executor()
+    executor.set_risk_validator(risk_validator)
+
+    order = Order("AAPL", 100, OrderType.Limit, 150.0, datetime.now(), client_order_id="resting-1")
+    executor.execute(order)
+    assert sent[-1]["request_type"] == "new"
+    executor.apply_report(order.order_id, ExecutionReport(ExecType.Accepted))
+    executor.apply_report(order.order_id, ExecutionReport(ExecType.Fill, 30, 150.0))
+
+    # Amendments are re-validated before reaching the broker
+    with pytest.raises(ValueError):
+        executor.replace(order.order_id, 2000, 150.0)
+    with pytest.raises(ValueError):
+        executor.replace(order.order_id, 100, 20000.0)
+    with pytest.raises(ValueError):
+        executor.replace(order.order_id, 20, 150.0)
+    assert executor.get_order_state(order.order_id).status == OrderStatus.PartiallyFilled
+
+    executor.replace(order.order_id, 200, 149.5)
+    assert sent[-1]["request_type"] == "replace"
+    assert sent[-1]["order_id"] == order.order_id
+    assert sent[-1]["quantity"] == 200
+    assert sent[-1]["price"] == 149.5
+    assert executor.get_order_state(order.order_id).status == OrderStatus.PendingReplace
+
+    executor.apply_report(order.order_id, ExecutionReport(ExecType.Replaced))
+    state = executor.get_order_state(order.order_id)
+    assert state.status == OrderStatus.PartiallyFilled
+    assert state.order.quantity == 200
+    assert state.order.price == 149.5
+    assert state.leaves_quantity() == 170
+
+    executor.cancel(order.order_id)
+    assert sent[-1] == {"request_type": "cancel", "order_id": order.order_id, "client_order_id": "resting-1", "symbol": "AAPL", "side": "Buy"}
+    assert executor.get_order_state(order.order_id).status == OrderStatus.PendingCancel
+    executor.apply_report(order.order_id, ExecutionReport(ExecType.Cancelled))
+
+    # Closed orders can be neither cancelled nor amended
+    with pytest.raises(ValueError):
+        executor.cancel(order.order_id)
+    with pytest.raises(ValueError):
+        executor.replace(order.order_id, 100, 150.0)
+    with pytest.raises(KeyError):
+        executor.cancel(123456789)
+
+def test_time_in_force():
+    executor, sent = make_executor()
+    validator = OrderValidator()
+    validator.add_symbol("AAPL")
+    validator.set_rule(OrderType.Stop, ValidationRule(min_quantity=1, max_quantity=1000, min_price=0.01, max_price=10000))
+    validator.set_rule(OrderType.Limit, ValidationRule(min_quantity=1, max_quantity=1000, min_price=0.01, max_price=10000))
+
+    validator.validate(Order("AAPL", 10, OrderType.Stop, 140.0, datetime.now(), time_in_force=TimeInForce.Gtc))
+    with pytest.raises(ValueError):
+        validator.validate(Order("AAPL", 10, OrderType.Stop, 140.0, datetime.now(), time_in_force=TimeInForce.Fok))
+    with pytest.raises(ValueError):
+        validator.validate(Order("AAPL", 10, OrderType.Stop, 140.0, datetime.now(), time_in_force=TimeInForce.Opg))
+    with pytest.raises(ValueError):
+        validator.validate(Order("AAPL", 10, OrderType.Limit, 150.0, datetime.now(), time_in_force=TimeInForce.Gtd))
+    with pytest.raises(ValueError):
+        validator.validate(Order("AAPL", 10, OrderType.Limit, 150.0, datetime.now(), time_in_force=TimeInForce.Gtd,
+                                 expire_time=datetime.now() - timedelta(hours=1)))
+    with pytest.raises(ValueError):
+        validator.validate(Order("AAPL", 10, OrderType.Limit, 150.0, datetime.now(), expire_time=datetime.now() + timedelta(hours=1)))
+
+    # The engine expires session orders at the close and GTD orders once due
+    now = datetime.now()
+    day = Order("AAPL", 10, OrderType.Limit, 150.0, now)
+    more_days = [Order("AAPL", 10, OrderType.Limit, 150.0, now) for _ in range(5)]
+    gtc = Order("AAPL", 10, OrderType.Limit, 150.0, now, time_in_force=TimeInForce.Gtc)
+    gtd = Order("AAPL", 10, OrderType.Limit, 150.0, now, time_in_force=TimeInForce.Gtd, expire_time=now + timedelta(hours=1))
+    for order in (day, gtc, gtd):
+        executor.execute(order)
+        executor.apply_report(order.order_id, ExecutionReport(ExecType.Accepted))
+    assert sent[0]["time_in_force"] == "DAY"
+    assert sent[2]["time_in_force"] == "GTD"
+    assert sent[2]["expire_time"] == gtd.expire_time
+
+    assert executor.expire_orders((now + timedelta(minutes=30)).timestamp()) == []
+    for order in more_days:
+        executor.execute(order)
+    # Orders expire in id order
+    expired = [day.order_id] + [order.order_id for order in more_days]
+    assert executor.on_session_close((now + timedelta(minutes=30)).timestamp()) == sorted(expired)
+    assert executor.expire_orders((now + timedelta(hours=2)).timestamp()) == [gtd.order_id]
+    assert executor.get_order_state(gtd.order_id).status == OrderStatus.Expired
+    assert [s.order_id for s in executor.open_orders()] == [gtc.order_id]
+
+def test_stop_and_trailing_prices():
+    validator = OrderValidator()
+    validator.add_symbol("AAPL")
+    for order_type in (OrderType.Stop, OrderType.StopLimit, OrderType.TrailingStop, OrderType.TrailingStopLimit, OrderType.Limit):
+        validator.set_rule(order_type, ValidationRule(min_quantity=1, max_quantity=1000, min_price=100.0, max_price=200.0))
+    executor, sent = make_executor(validator)
+
+    # A plain stop keeps its trigger in the positional price
+    stop = Order("AAPL", 10, OrderType.Stop, 140.0, datetime.now(), side=Side.Sell)
+    assert stop.stop_price == 140.0
+    assert stop.limit_price is None
+
+    stop_limit = Order("AAPL", 10, OrderType.StopLimit, 139.5, datetime.now(), side=Side.Sell, stop_price=140.0)
+    assert stop_limit.limit_price == 139.5
+    executor.execute(stop_limit)
+    assert sent[-1]["limit_price"] == 139.5
+    assert sent[-1]["stop_price"] == 140.0
+
+    # A replace can move the trigger as well as the limit
+    executor.apply_report(stop_limit.order_id, ExecutionReport(ExecType.Accepted))
+    executor.replace(stop_limit.order_id, 10, 138.5, new_stop_price=139.0)
+    assert sent[-1]["request_type"] == "replace"
+    assert (sent[-1]["limit_price"], sent[-1]["stop_price"]) == (138.5, 139.0)
+    executor.apply_report(stop_limit.order_id, ExecutionReport(ExecType.Replaced))
+    replaced = executor.get_order_state(stop_limit.order_id).order
+    assert (replaced.limit_price, replaced.stop_price) == (Decimal("138.5"), Decimal("139.0"))
+
+    # The limit is stored with the order and only limit-style orders have one
+    stop_limit.limit_price = Decimal("139")
+    assert stop_limit.price == 139
+    with pytest.raises(ValueError):
+        stop.limit_price = Decimal("139")
+    stop.order_type = OrderType.Limit
+    assert stop.limit_price == 140.0
+
+    with pytest.raises(ValueError):
+        validator.validate(Order("AAPL", 10, OrderType.StopLimit, 139.5, datetime.now()))
+    # Both prices must sit inside the band
+    with pytest.raises(ValueError):
+        validator.validate(Order("AAPL", 10, OrderType.StopLimit, 139.5, datetime.now(), stop_price=90.0))
+    with pytest.raises(ValueError):
+        validator.validate(Order("AAPL", 10, OrderType.Limit, 150.0, datetime.now(), stop_price=140.0))
+
+    trailing = Order("AAPL", 10, OrderType.TrailingStop, 150.0, datetime.now(), side=Side.Sell, trail_percent=2.5)
+    executor.execute(trailing)
+    assert sent[-1]["trail_percent"] == 2.5
+    assert "price" not in sent[-1]
+    with pytest.raises(ValueError):
+        validator.validate(Order("AAPL", 10, OrderType.TrailingStop, 150.0, datetime.now()))
+    with pytest.raises(ValueError):
+        validator.validate(Order("AAPL", 10, OrderType.TrailingStopLimit, 150.0, datetime.now(), trail_amount=1.0, trail_percent=1.0))
+    validator.validate(Order("AAPL", 10, OrderType.TrailingStopLimit, 150.0, datetime.now(), trail_amount=1.0))
+
+def test_contingent_orders():
+    validator = OrderValidator()
+    validator.add_symbol("AAPL")
+    for order_type in (OrderType.Market, OrderType.Limit, OrderType.Stop):
+        validator.set_rule(order_type, ValidationRule(min_quantity=1, max_quantity=1000, min_price=0, max_price=10000))
+    executor, sent = make_executor(validator)
+
+    entry = Order("AAPL", 100, OrderType.Limit, 150.0, datetime.now())
+    take_profit = Order("AAPL", 100, OrderType.Limit, 160.0, datetime.now(), side=Side.Sell)
+    stop_loss = Order("AAPL", 100, OrderType.Stop, 145.0, datetime.now(), side=Side.Sell)
+
+    # Exits must close the entry
+    with pytest.raises(ValueError):
+        executor.submit_bracket(entry, take_profit, Order("AAPL", 100, OrderType.Stop, 145.0, datetime.now()))
+
+    executor.submit_bracket(entry, take_profit, stop_loss)
+    assert [o["order_id"] for o in sent] == [entry.order_id]
+    group = executor.get_order_group(entry.order_id)
+    assert group.contingency_type == ContingencyType.Bracket
+    assert sorted(group.oco_ids) == sorted([take_profit.order_id, stop_loss.order_id])
+    assert len(executor.get_held_orders()) == 2
+    with pytest.raises(ValueError):
+        executor.execute(stop_loss)
+
+    # Exits go out once the entry fills
+    executor.apply_report(entry.order_id, ExecutionReport(ExecType.Accepted))
+    executor.apply_report(entry.order_id, ExecutionReport(ExecType.Fill, 40, 150.0))
+    assert len(sent) == 1
+    executor.apply_report(entry.order_id, ExecutionReport(ExecType.Fill, 60, 150.0))
+    assert sorted(o["order_id"] for o in sent[1:]) == sorted([take_profit.order_id, stop_loss.order_id])
+    assert len(executor.take_contingent_results()) == 2
+
+    # The take-profit trading cancels the stop-loss
+    executor.apply_report(take_profit.order_id, ExecutionReport(ExecType.Accepted))
+    executor.apply_report(stop_loss.order_id, ExecutionReport(ExecType.Accepted))
+    executor.apply_report(take_profit.order_id, ExecutionReport(ExecType.Fill, 100, 160.0))
+    assert sent[-1]["request_type"] == "cancel"
+    assert sent[-1]["order_id"] == stop_loss.order_id
+    assert executor.get_order_state(stop_loss.order_id).status == OrderStatus.PendingCancel
+    executor.apply_report(stop_loss.order_id, ExecutionReport(ExecType.Cancelled))
+
+    # A parent that is cancelled unfilled discards its child
+    parent = Order("AAPL", 50, OrderType.Limit, 150.0, datetime.now())
+    child = Order("AAPL", 50, OrderType.Limit, 155.0, datetime.now(), side=Side.Sell)
+    executor.submit_oto(parent, child)
+    executor.apply_report(parent.order_id, ExecutionReport(ExecType.Accepted))
+    executor.apply_report(parent.order_id, ExecutionReport(ExecType.Cancelled))
+    assert executor.get_held_orders() == []
+    assert executor.get_order_state(child.order_id) is None
+
+    # OCO pairs are both working immediately
+    first = Order("AAPL", 10, OrderType.Limit, 149.0, datetime.now())
+    second = Order("AAPL", 10, OrderType.Stop, 151.0, datetime.now())
+    executor.submit_oco(first, second)
+    assert executor.get_order_state(first.order_id).status == OrderStatus.PendingNew
+    assert executor.get_order_state(second.order_id).status == OrderStatus.PendingNew
+    executor.apply_report(second.order_id, ExecutionReport(ExecType.Fill, 10, 151.0))
+    assert executor.get_order_state(first.order_id).status == OrderStatus.PendingCancel
+
+    # A group whose entry can't be sent leaves nothing held
+    restricted = RestrictedList()
+    executor.set_compliance(ComplianceChecker(restricted))
+    restricted.add("AAPL")
+    entry = Order("AAPL", 100, OrderType.Limit, 150.0, datetime.now())
+    take_profit = Order("AAPL", 100, OrderType.Limit, 160.0, datetime.now(), side=Side.Sell, client_order_id="tp-1")
+    stop_loss = Order("AAPL", 100, OrderType.Stop, 145.0, datetime.now(), side=Side.Sell)
+    with pytest.raises(RestrictedSymbolError):
+        executor.submit_bracket(entry, take_profit, stop_loss)
+    assert executor.get_held_orders() == [] and executor.get_order_group(entry.order_id) is None
+    restricted.remove("AAPL")
+
+    # Every exit is tried even if one fails, and exits that were never sent are handed back
+    executor.submit_bracket(entry, take_profit, stop_loss)
+    executor.apply_report(entry.order_id, ExecutionReport(ExecType.Accepted))
+    restricted.add("AAPL", RestrictionLevel.Restricted)
+    with pytest.raises(ValueError) as exc_info:
+        executor.apply_report(entry.order_id, ExecutionReport(ExecType.Fill, 100, 150.0))
+    assert sorted(o.order_id for o in exc_info.value.failed_orders) == sorted([take_profit.order_id, stop_loss.order_id])
+    assert executor.get_order_state(entry.order_id).status == OrderStatus.Filled
+    restricted.remove("AAPL")
+    executor.execute(exc_info.value.failed_orders[0])
+
+    calls = []
+    def flaky(order_dict):
+        calls.append(order_dict["order_id"])
+        if order_dict["order_id"] == take_profit.order_id:
+            raise ConnectionError("broker down")
+        return order_dict
+    executor = OrderExecutor(validator, flaky)
+    take_profit = Order("AAPL", 100, OrderType.Limit, 160.0, datetime.now(), side=Side.Sell)
+    stop_loss = Order("AAPL", 100, OrderType.Stop, 145.0, datetime.now(), side=Side.Sell)
+    executor.submit_bracket(entry, take_profit, stop_loss)
+    executor.apply_report(entry.order_id, ExecutionReport(ExecType.Accepted))
+    with pytest.raises(ValueError) as exc_info:
+        executor.apply_report(entry.order_id, ExecutionReport(ExecType.Fill, 100, 150.0))
+    assert exc_info.value.failed_orders == []
+    assert stop_loss.order_id in calls
+    assert executor.get_order_state(stop_loss.order_id).status == OrderStatus.PendingNew
+    assert executor.get_order_state(take_profit.order_id).status == OrderStatus.PendingNew
+
+def test_decimal_prices_and_quantities():
+    # Values come back as exact decimals whether given as str, Decimal, int or float
+    order = Order("AAPL", "0.3", OrderType.Limit, Decimal("100.10"), datetime.now())
+    assert order.quantity == Decimal("0.3")
+    assert order.price == Decimal("100.10")
+    assert isinstance(order.price, Decimal)
+    assert Order("AAPL", 0.1, OrderType.Limit, 0.7, datetime.now()).price == Decimal("0.7")
+    assert Order("AAPL", 3, OrderType.Limit, 10, datetime.now()).signed_quantity() == 3
+
+    # 0.1 + 0.2 style artifacts no longer leak into average prices
+    tracker = OrderStateTracker()
+    order = Order("AAPL", 2, OrderType.Limit, Decimal("0.3"), datetime.now())
+    tracker.track(order)
+    tracker.apply_report(order.order_id, ExecutionReport(ExecType.PendingNew))
+    tracker.apply_report(order.order_id, ExecutionReport(ExecType.Fill, 1, 0.1))
+    tracker.apply_report(order.order_id, ExecutionReport(ExecType.Fill, 1, 0.2))
+    state = tracker.get_state(order.order_id)
+    assert state.filled_quantity == 2
+    assert state.avg_fill_price == Decimal("0.15")
+
+    # Notional checks sit exactly on the limit instead of straddling it
+    risk_validator = RiskValidator(max_position_size=1000, max_daily_loss=5000, max_order_value=Decimal("30.03"))
+    assert risk_validator.validate_order(Order("AAPL", 3, OrderType.Limit, Decimal("10.01"), datetime.now()), 0, 0)
+    assert not risk_validator.validate_order(Order("AAPL", 3, OrderType.Limit, Decimal("10.02"), datetime.now()), 0, 0)
+
+    validator = OrderValidator()
+    validator.add_symbol("AAPL")
+    validator.set_rule(OrderType.Limit, ValidationRule(min_quantity=1, max_quantity=float('inf'), min_price=Decimal("0.01"), max_price=float('inf')))
+    validator.validate(Order("AAPL", 10**12, OrderType.Limit, Decimal("0.01"), datetime.now()))
+    with pytest.raises(ValueError):
+        validator.validate(Order("AAPL", 10, OrderType.Limit, Decimal("0.009"), datetime.now()))
+
+def test_instrument_registry():
+    expiry = (datetime.now() + timedelta(days=30)).timestamp()
+    registry = InstrumentRegistry()
+    registry.add(Instrument("AAPL", AssetClass.Equity, "NASDAQ", "USD"))
+    registry.add(Instrument("ESZ5", AssetClass.Future, "CME", "USD", tick_size=Decimal("0.25"), multiplier=50, expiry=expiry))
+    registry.add(Instrument("AAPL 250C", AssetClass.Option, "CBOE", "USD", multiplier=100, expiry=expiry, strike=250, right=OptionRight.Call))
+    registry.add(Instrument("OLDF", AssetClass.Future, "CME", "USD", expiry=(datetime.now() - timedelta(days=1)).timestamp()))
+    assert len(registry) == 4
+    assert registry.get("ESZ5").tick_size == Decimal("0.25")
+
+    with pytest.raises(ValueError):
+        Instrument("ESZ5", AssetClass.Future, "CME", "USD")
+    with pytest.raises(ValueError):
+        Instrument("AAPL 250C", AssetClass.Option, "CBOE", "USD", expiry=expiry, strike=250)
+    with pytest.raises(ValueError):
+        Instrument("AAPL", AssetClass.Equity, "NASDAQ", "USD", right=OptionRight.Put)
+
+    # Validator and risk validator share the same registry
+    validator = OrderValidator()
+    validator.set_instruments(registry)
+    validator.set_rule(OrderType.Limit, ValidationRule(min_quantity=1, max_quantity=1000, min_price=0.01, max_price=10000))
+    validator.validate(Order("ESZ5", 2, OrderType.Limit, 5000, datetime.now()))
+    with pytest.raises(ValueError):
+        validator.validate(Order("OLDF", 2, OrderType.Limit, 5000, datetime.now()))
+    with pytest.raises(ValueError):
+        validator.validate(Order("MSFT", 2, OrderType.Limit, 300, datetime.now()))
+    registry.add(Instrument("MSFT", AssetClass.Equity, "NASDAQ", "USD"))
+    validator.validate(Order("MSFT", 2, OrderType.Limit, 300, datetime.now()))
+
+    # Notional includes the contract multiplier
+    risk_validator = RiskValidator(max_position_size=1000, max_daily_loss=5000, max_order_value=400000)
+    future_order = Order("ESZ5", 2, OrderType.Limit, 5000, datetime.now())
+    assert risk_validator.validate_order(future_order, 0, 0)
+    risk_validator.set_instruments(registry)
+    assert registry.get("ESZ5").notional(2, 5000) == 500000
+    assert not risk_validator.validate_order(future_order, 0, 0)
+
+def test_tick_and_lot_conformance():
+    registry = InstrumentRegistry()
+    registry.add(Instrument("AAPL", AssetClass.Equity, "NASDAQ", "USD", lot_size=10,
+                            tick_table=TickTable([(0, Decimal("0.0001")), (1, Decimal("0.01"))])))
+    assert registry.get("AAPL").tick_for(Decimal("0.5")) == Decimal("0.0001")
+    assert registry.get("AAPL").tick_for(150) == Decimal("0.01")
+    with pytest.raises(ValueError):
+        TickTable([(1, Decimal("0.01")), (0, Decimal("0.0001"))])
+
+    validator = OrderValidator()
+    validator.set_instruments(registry)
+    validator.set_rule(OrderType.Limit, ValidationRule(min_quantity=1, max_quantity=1000, min_price=0.0001, max_price=10000))
+    validator.set_rule(OrderType.Stop, ValidationRule(min_quantity=1, max_quantity=1000, min_price=0.0001, max_price=10000))
+    validator.validate(Order("AAPL", 100, OrderType.Limit, Decimal("150.01"), datetime.now()))
+    validator.validate(Order("AAPL", 100, OrderType.Limit, Decimal("0.5001"), datetime.now()))
+    with pytest.raises(ValueError):
+        validator.validate(Order("AAPL", 100, OrderType.Limit, Decimal("150.005"), datetime.now()))
+    with pytest.raises(ValueError):
+        validator.validate(Order("AAPL", 105, OrderType.Limit, Decimal("150.01"), datetime.now()))
+    # Bare symbols have no grid to conform to
+    validator.add_symbol("PENNY")
+    validator.validate(Order("PENNY", Decimal("2.5"), OrderType.Limit, Decimal("0.12345"), datetime.now()))
+
+    # Auto-round snaps prices in the order's favour and quantities down to a lot
+    validator.set_auto_round(True)
+    buy = validator.conform(Order("AAPL", 105, OrderType.Limit, Decimal("150.017"), datetime.now()))
+    assert buy.price == Decimal("150.01") and buy.quantity == 100
+    sell = validator.conform(Order("AAPL", -105, OrderType.Limit, Decimal("150.011"), datetime.now()))
+    assert sell.price == Decimal("150.02")
+    sell_stop = validator.conform(Order("AAPL", -100, OrderType.Stop, Decimal("149.999"), datetime.now()))
+    assert sell_stop.stop_price == Decimal("149.99")
+    with pytest.raises(ValueError):
+        validator.conform(Order("AAPL", 100, OrderType.Limit, Decimal("70000000000000000000000000000"), datetime.now()))
+    validator.validate(Order("AAPL", 105, OrderType.Limit, Decimal("150.017"), datetime.now()))
+
+    executor, sent = make_executor(validator)
+    executor.execute(Order("AAPL", 105, OrderType.Limit, Decimal("150.017"), datetime.now()))
+    assert sent[-1]['quantity'] == 100
+    assert sent[-1]['price'] == Decimal("150.01")
+
+def test_layered_validation_rules():
+    validator = OrderValidator()
+    validator.add_symbol("AAPL")
+    validator.add_symbol("PENNY")
+    validator.set_global_rule(ValidationRule(min_quantity=1, max_quantity=10000, min_price=0.01, max_price=100000))
+    validator.set_rule(OrderType.Limit, ValidationRule(max_quantity=1000))
+    validator.set_instrument_rule("PENNY", ValidationRule(max_price=5))
+    validator.set_account_rule("small", ValidationRule(max_quantity=10))
+
+    # Order types without their own rule fall back to the global rule
+    validator.validate(Order("AAPL", 5000, OrderType.Market, 0, datetime.now()))
+    validator.validate(Order("AAPL", 500, OrderType.Limit, 150, datetime.now()))
+
+    with pytest.raises(ValueError, match="Limit order type rule"):
+        validator.validate(Order("AAPL", 5000, OrderType.Limit, 150, datetime.now()))
+    with pytest.raises(ValueError, match="instrument rule for PENNY"):
+        validator.validate(Order("PENNY", 500, OrderType.Limit, 6, datetime.now()))
+    with pytest.raises(ValueError, match="account rule for small"):
+        validator.validate(Order("AAPL", 50, OrderType.Limit, 150, datetime.now(), account="small"))
+    with pytest.raises(ValueError, match="global rule"):
+        validator.validate(Order("AAPL", 20000, OrderType.Market, 0, datetime.now()))
+
+    # The most specific layer wins, even when it is looser
+    validator.set_account_rule("big", ValidationRule(max_quantity=5000))
+    validator.validate(Order("AAPL", 5000, OrderType.Limit, 150, datetime.now(), account="big"))
+
+def test_structured_validation_errors():
+    validator = OrderValidator()
+    validator.add_symbol("AAPL")
+    validator.set_rule(OrderType.Limit, ValidationRule(min_quantity=1, max_quantity=1000, min_price=0.01, max_price=10000))
+
+    with pytest.raises(UnknownSymbolError) as exc_info:
+        validator.validate(Order("MSFT", 100, OrderType.Limit, 300, datetime.now()))
+    assert exc_info.value.symbol == "MSFT"
+
+    with pytest.raises(QuantityOutOfRangeError) as exc_info:
+        validator.validate(Order("AAPL", 5000, OrderType.Limit, 150, datetime.now()))
+    assert exc_info.value.quantity == 5000
+    assert exc_info.value.limit == 1000
+    assert exc_info.value.bound == "maximum"
+    assert exc_info.value.layer == RuleLayer.OrderType
+
+    with pytest.raises(PriceOutOfRangeError) as exc_info:
+        validator.validate(Order("AAPL", 100, OrderType.Limit, 20000, datetime.now()))
+    assert exc_info.value.price == 20000
+
+    with pytest.raises(NoRuleForTypeError) as exc_info:
+        validator.validate(Order("AAPL", 100, OrderType.Market, 0, datetime.now()))
+    assert exc_info.value.order_type == OrderType.Market
+
+    validator.set_max_order_age(60)
+    with pytest.raises(OrderTooOldError) as exc_info:
+        validator.validate(Order("AAPL", 100, OrderType.Limit, 150, datetime.now() - timedelta(minutes=5)))
+    assert exc_info.value.max_age == 60
+    assert exc_info.value.age > 60
+
+    # Every validation error is still a ValueError
+    assert issubclass(OrderTooOldError, OrderValidationError)
+    assert issubclass(OrderValidationError, ValueError)
+
+def test_validation_report():
+    validator = OrderValidator()
+    validator.add_instrument(Instrument("AAPL", AssetClass.Equity, "NASDAQ", "USD", lot_size=10))
+    validator.set_rule(OrderType.Limit, ValidationRule(min_quantity=10, max_quantity=1000, min_price=0.01, max_price=200))
+    for bad_threshold in [Decimal("-0.01"), Decimal("1.5")]:
+        with pytest.raises(ValueError):
+            validator.set_band_warning_threshold(bad_threshold)
+    validator.set_band_warning_threshold(Decimal("0.05"))
+
+    # Every failure is reported, not just the first
+    bad = Order("AAPL", 5005, OrderType.Limit, Decimal("250.001"), datetime.now(), trail_amount=1)
+    report = validator.validate_all(bad)
+    assert not report.is_valid
+    codes = [v.code for v in report.errors()]
+    assert codes == ["LotSize", "TickSize", "QuantityOutOfRange", "PriceOutOfRange", "TrailNotAllowed"]
+    assert report.errors()[0].details["lot_size"] == 10
+    assert all(v.severity == Severity.Error for v in report.errors())
+
+    # validate still raises the first of them
+    with pytest.raises(LotSizeError):
+        validator.validate(bad)
+
+    # A price near the band edge is recorded but lets the order through
+    near_edge = Order("AAPL", 100, OrderType.Limit, 195, datetime.now())
+    report = validator.validate_all(near_edge)
+    assert report.is_valid
+    assert [v.code for v in report.warnings()] == ["NearBandEdge"]
+    assert report.warnings()[0].details["bound"] == "maximum"
+    validator.validate(near_edge)
+
+    reports = validator.validate_all_multiple([bad, near_edge, Order("AAPL", 100, OrderType.Limit, 150, datetime.now())])
+    assert [len(r) for r in reports] == [5, 1, 0]
+
+def test_custom_validation_checks():
+    validator = OrderValidator()
+    validator.add_symbol("AAPL")
+    validator.add_symbol("XYZ")
+    validator.set_rule(OrderType.Limit, ValidationRule(min_quantity=1, max_quantity=1000, min_price=0.01, max_price=10000))
+
+    calls = []
+    def restricted_list(order, instrument):
+        calls.append("restricted")
+        if instrument.symbol == "XYZ":
+            return "XYZ is on the restricted list"
+    def earnings_blackout(order, instrument):
+        calls.append("blackout")
+        if order.quantity > 500:
+            return (Severity.Warning, "large order during earnings week")
+    def broken(order, instrument):
+        raise RuntimeError("compliance service unavailable")
+
+    validator.add_check("earnings_blackout", earnings_blackout, priority=10)
+    validator.add_check("restricted_list", restricted_list)
+    assert validator.check_names() == ["restricted_list", "earnings_blackout"]
+
+    validator.validate(Order("AAPL", 100, OrderType.Limit, 150, datetime.now()))
+    assert calls == ["restricted", "blackout"]
+
+    with pytest.raises(CustomCheckError) as exc_info:
+        validator.validate(Order("XYZ", 100, OrderType.Limit, 50, datetime.now()))
+    assert exc_info.value.check == "restricted_list"
+
+    report = validator.validate_all(Order("AAPL", 800, OrderType.Limit, 150, datetime.now()))
+    assert report.is_valid
+    assert report.warnings()[0].details["check"] == "earnings_blackout"
+
+    # A check that raises fails the order rather than letting it through
+    validator.add_check("broken", broken)
+    with pytest.raises(CustomCheckError):
+        validator.validate(Order("AAPL", 100, OrderType.Limit, 150, datetime.now()))
+    assert validator.remove_check("broken")
+    validator.validate(Order("AAPL", 100, OrderType.Limit, 150, datetime.now()))
+
+def test_price_collars_and_fat_finger():
+    validator = OrderValidator()
+    validator.add_symbol("AAPL")
+    validator.add_instrument(Instrument("MSFT", AssetClass.Equity, "NASDAQ", "USD", tick_size=Decimal("0.01")))
+    validator.set_rule(OrderType.Market, ValidationRule(min_quantity=1, max_quantity=100000, min_price=0, max_price=float('inf')))
+    validator.set_rule(OrderType.Limit, ValidationRule(min_quantity=1, max_quantity=100000, min_price=0.01, max_price=10000))
+    validator.set_collar(PriceCollar(ReferencePrice.Last, max_percent=Decimal("5")))
+    validator.set_instrument_collar("MSFT", PriceCollar(ReferencePrice.Mid, max_ticks=20))
+
+    # Prices pushed by the feed reach validators that share the store
+    market_data = MarketDataStore()
+    validator.set_market_data(market_data)
+    market_data.update("AAPL", MarketData(last=Decimal("100"), previous_close=Decimal("98"), average_daily_volume=10000))
+    market_data.update("MSFT", MarketData(bid=Decimal("300.00"), ask=Decimal("300.10")))
+    executor, sent = make_executor(validator)
+
+    validator.validate(Order("AAPL", 100, OrderType.Limit, Decimal("104.99"), datetime.now()))
+    with pytest.raises(PriceCollarError) as exc_info:
+        validator.validate(Order("AAPL", 100, OrderType.Limit, Decimal("105.01"), datetime.now()))
+    assert exc_info.value.reference_price == 100
+    assert exc_info.value.reference == ReferencePrice.Last
+    with pytest.raises(PriceCollarError):
+        executor.execute(Order("AAPL", 100, OrderType.Limit, Decimal("90"), datetime.now()))
+    # Market orders carry no limit price to collar
+    validator.validate(Order("AAPL", 100, OrderType.Market, 0, datetime.now()))
+
+    # The MSFT collar is 20 ticks either side of the mid
+    validator.validate(Order("MSFT", 100, OrderType.Limit, Decimal("300.25"), datetime.now()))
+    with pytest.raises(PriceCollarError) as exc_info:
+        validator.validate(Order("MSFT", 100, OrderType.Limit, Decimal("300.30"), datetime.now()))
+    assert exc_info.value.deviation_ticks == 25
+
+    # A missing reference price rejects the order unless flagged as a warning
+    validator.add_symbol("IBM")
+    with pytest.raises(PriceCollarError) as exc_info:
+        validator.validate(Order("IBM", 100, OrderType.Limit, 150, datetime.now()))
+    assert exc_info.value.symbol == "IBM" and exc_info.value.reference == ReferencePrice.Last
+    validator.set_missing_reference_severity(Severity.Warning)
+    report = validator.validate_all(Order("IBM", 100, OrderType.Limit, 150, datetime.now()))
+    assert report.is_valid
+    assert [v.code for v in report.warnings()] == ["MissingReferencePrice"]
+    # Quotes too large to average leave no mid to collar against
+    extreme = MarketData(bid=Decimal("5e28"), ask=Decimal("5e28"))
+    assert extreme.mid is None
+    market_data.update("MSFT", extreme)
+    report = validator.validate_all(Order("MSFT", 100, OrderType.Limit, 150, datetime.now()))
+    assert [v.code for v in report.warnings()] == ["MissingReferencePrice"]
+
+    # Fat-finger check against a tenth of the average daily traded value (10000 x 98)
+    validator.set_max_adv_multiple(Decimal("0.1"))
+    validator.validate(Order("AAPL", 900, OrderType.Limit, 100, datetime.now()))
+    with pytest.raises(AdvExceededError) as exc_info:
+        validator.validate(Order("AAPL", 1001, OrderType.Market, 0, datetime.now()))
+    assert exc_info.value.adv_notional == 980000
+    # A multiple of an enormous ADV is no limit at all
+    market_data.update("AAPL", MarketData(last=Decimal("100"), previous_close=Decimal("98"), average_daily_volume=Decimal("1e26")))
+    validator.set_max_adv_multiple(Decimal("10"))
+    validator.validate(Order("AAPL", 1001, OrderType.Market, 0, datetime.now()))
+
+def test_trading_calendar(tmp_path):
+    config = tmp_path / "calendar.toml"
+    config.write_text("""
+[exchanges.NYSE]
+timezone = "America/New_York"
+pre_market_open = "04:00"
+regular_open = "09:30"
+regular_close = "16:00"
+post_market_close = "20:00"
+holidays = ["2025-12-25"]
+half_days = { "2025-11-28" = "13:00" }
+""")
+    calendar = TradingCalendar.from_file(str(config))
+    nyse = calendar.get("NYSE")
+    new_york = ZoneInfo("America/New_York")
+    at = lambda *args: datetime(*args, tzinfo=new_york).timestamp()
+    assert nyse.session_at(at(2025, 11, 26, 8, 0)) == TradingSession.PreMarket
+    assert nyse.session_at(at(2025, 11, 26, 10, 0)) == TradingSession.Regular
+    assert nyse.session_at(at(2025, 11, 26, 17, 0)) == TradingSession.PostMarket
+    assert nyse.session_at(at(2025, 11, 26, 21, 0)) == TradingSession.Closed
+    assert nyse.session_at(at(2025, 11, 29, 10, 0)) == TradingSession.Closed  # Saturday
+    assert nyse.session_at(at(2025, 12, 25, 10, 0)) == TradingSession.Closed  # Holiday
+    assert nyse.session_at(at(2025, 11, 28, 14, 0)) == TradingSession.PostMarket  # Half day
+    with pytest.raises(ValueError):
+        ExchangeCalendar("BAD", "Mars/Olympus", "09:30", "16:00")
+    # Sessions can't wrap past midnight, and an early close must fall inside the regular session
+    with pytest.raises(ValueError):
+        ExchangeCalendar("CME", "America/Chicago", "17:00", "16:00")
+    with pytest.raises(ValueError):
+        ExchangeCalendar("BAD", "America/New_York", "09:30", "16:00", half_days={"2025-11-28": "09:00"})
+    with pytest.raises(ValueError):
+        nyse.add_half_day("2025-12-24", "09:30")
+    nyse.add_half_day("2025-12-24", "13:00")
+
+    # Sessions checked against a fixed clock, midday on a Wednesday
+    now = datetime(2025, 11, 26, 12, 0, tzinfo=ZoneInfo("UTC"))
+    always_open = ExchangeCalendar("OPEN", "UTC", "00:00", "23:59:59", weekend=[])
+    closed_today = ExchangeCalendar("SHUT", "UTC", "00:00", "23:59:59", weekend=[], holidays=["2025-11-26"])
+    pre_market = ExchangeCalendar("EARLY", "UTC", "13:00", "20:00", pre_market_open="00:00", weekend=[])
+    calendar = TradingCalendar()
+    for exchange_calendar in [always_open, closed_today, pre_market]:
+        calendar.add(exchange_calendar)
+
+    validator = OrderValidator()
+    validator.set_clock(Clock.fixed(now))
+    validator.set_calendar(calendar)
+    validator.set_rule(OrderType.Limit, ValidationRule(min_quantity=1, max_quantity=1000, min_price=0.01, max_price=10000))
+    for symbol, exchange in [("AAPL", "OPEN"), ("SHUT", "SHUT"), ("EARLY", "EARLY"), ("OTC", "NOCAL")]:
+        validator.add_instrument(Instrument(symbol, AssetClass.Equity, exchange, "USD"))
+
+    validator.validate(Order("AAPL", 100, OrderType.Limit, 150, now))
+    with pytest.raises(OutsideTradingHoursError) as exc_info:
+        validator.validate(Order("SHUT", 100, OrderType.Limit, 150, now, extended_hours=True))
+    assert exc_info.value.session == TradingSession.Closed
+    with pytest.raises(OutsideTradingHoursError):
+        validator.validate(Order("EARLY", 100, OrderType.Limit, 150, now))
+    validator.validate(Order("EARLY", 100, OrderType.Limit, 150, now, extended_hours=True))
+    # Exchanges without a calendar are not checked
+    validator.validate(Order("OTC", 100, OrderType.Limit, 150, now))
+
+    # Flag instead of reject
+    validator.set_market_hours_severity(Severity.Warning)
+    report = validator.validate_all(Order("SHUT", 100, OrderType.Limit, 150, now))
+    assert report.is_valid
+    assert [v.code for v in report.warnings()] == ["OutsideTradingHours"]
+
+def test_injectable_clock():
+    start = datetime(2024, 3, 1, 15, 0)
+    clock = Clock.simulated(start)
+    assert clock.is_simulated
+    assert clock.now() == start.timestamp()
+
+    validator = OrderValidator()
+    validator.add_symbol("AAPL")
+    validator.set_rule(OrderType.Limit, ValidationRule(min_quantity=1, max_quantity=1000, min_price=0.01, max_price=10000))
+    validator.set_max_order_age(60)
+    executor, sent = make_executor(validator)
+    executor.set_clock(clock)
+
+    # Orders are aged against the simulated time, not the wall clock
+    order = Order("AAPL", 100, OrderType.Limit, 150, start)
+    clock.advance(30)
+    executor.execute(order)
+    clock.advance(60)
+    with pytest.raises(OrderTooOldError):
+        executor.execute(Order("AAPL", 100, OrderType.Limit, 150, start))
+
+    # GTD expiry follows the clock when no timestamp is given
+    gtd = Order("AAPL", 100, OrderType.Limit, 150, clock.now(), time_in_force=TimeInForce.Gtd, expire_time=clock.now() + 3600)
+    executor.execute(gtd)
+    assert executor.expire_orders() == []
+    clock.advance(3600)
+    assert executor.expire_orders() == [gtd.order_id]
+
+    with pytest.raises(ValueError):
+        clock.set(start)
+    for bad in [float("nan"), float("inf")]:
+        with pytest.raises(ValueError):
+            clock.advance(bad)
+        with pytest.raises(ValueError):
+            clock.set(bad)
+        with pytest.raises(ValueError):
+            Clock.fixed(bad)
+    assert clock.now() == start.timestamp() + 30 + 60 + 3600
+    with pytest.raises(TypeError):
+        Clock.fixed(start).advance(1)
+    assert Clock.fixed(start).now() == start.timestamp()
+    assert abs(Clock.wall().now() - datetime.now().timestamp()) < 5
+
+def test_restricted_list_and_locates():
+    restricted = RestrictedList()
+    restricted.add("XYZ", reason="pending merger")
+    restricted.add("ABC", RestrictionLevel.Watch)
+    locates = LocateInventory()
+    locates.add_locate("AAPL", 300)
+    locates.set_hard_to_borrow("GME", True)
+    locates.add_locate("GME", 100)
+    compliance = ComplianceChecker(restricted, locates)
+
+    report = compliance.check(Order("XYZ", 100, OrderType.Limit, 50, datetime.now()))
+    assert [v.code for v in report.errors()] == ["RestrictedSymbol"]
+    assert report.errors()[0].details["reason"] == "pending merger"
+    report = compliance.check(Order("ABC", 100, OrderType.Limit, 50, datetime.now()))
+    assert report.is_valid and [v.code for v in report.warnings()] == ["WatchListSymbol"]
+
+    # Only the part of a sale that takes the account short needs a locate
+    assert compliance.check(Order("AAPL", -500, OrderType.Limit, 150, datetime.now()), current_position=200).is_valid
+    report = compliance.check(Order("AAPL", -600, OrderType.Limit, 150, datetime.now()), current_position=200)
+    assert report.errors()[0].details["short_quantity"] == 400
+    report = compliance.check(Order("GME", 50, OrderType.Limit, 20, datetime.now(), side=Side.SellShort))
+    assert report.is_valid and [v.code for v in report.warnings()] == ["HardToBorrow"]
+
+    validator = OrderValidator()
+    for symbol in ["AAPL", "XYZ"]:
+        validator.add_symbol(symbol)
+    validator.set_rule(OrderType.Limit, ValidationRule(min_quantity=1, max_quantity=1000, min_price=0.01, max_price=10000))
+    executor, sent = make_executor(validator)
+    executor.set_compliance(compliance)
+
+    with pytest.raises(RestrictedSymbolError):
+        executor.execute(Order("XYZ", 100, OrderType.Limit, 50, datetime.now()))
+    # The list can change while the engine runs
+    restricted.remove("XYZ")
+    executor.execute(Order("XYZ", 100, OrderType.Limit, 50, datetime.now()))
+
+    # Each short sale uses up its locates
+    first = Order("AAPL", -200, OrderType.Limit, 150, datetime.now())
+    executor.execute(first)
+    assert locates.available("AAPL") == 100
+    with pytest.raises(LocateRequiredError) as exc_info:
+        executor.execute(Order("AAPL", -200, OrderType.Limit, 150, datetime.now()))
+    assert exc_info.value.available == 100
+    # Closing a long needs no locate, but working sales already count against it
+    with pytest.raises(LocateRequiredError):
+        executor.execute(Order("AAPL", -200, OrderType.Limit, 150, datetime.now()), current_position=200)
+    executor.execute(Order("AAPL", -200, OrderType.Limit, 150, datetime.now()), current_position=400)
+    assert locates.available("AAPL") == 100
+
+    # A short sale that dies unfilled returns its locates
+    executor.apply_report(first.order_id, ExecutionReport(ExecType.Rejected, reason="no borrow"))
+    assert locates.available("AAPL") == 300
+
+    # One that dies part-filled returns the locates of the rest
+    partial = Order("AAPL", -300, OrderType.Limit, 150, datetime.now(), account="acct-2")
+    executor.execute(partial)
+    assert locates.available("AAPL") == 0
+    executor.apply_report(partial.order_id, ExecutionReport(ExecType.Fill, 10, 150))
+    executor.apply_report(partial.order_id, ExecutionReport(ExecType.Cancelled))
+    assert locates.available("AAPL") == 290
+
+    # Two sales can't both close the same long
+    executor.get_ledger().set_position("XYZ", 100, 50, account="acct-3")
+    executor.execute(Order("XYZ", -100, OrderType.Limit, 50, datetime.now(), account="acct-3"))
+    with pytest.raises(LocateRequiredError):
+        executor.execute(Order("XYZ", -100, OrderType.Limit, 50, datetime.now(), account="acct-3"))
+
+    # Engine-sent children need locates too, while bracket exits only count once
+    oto_parent = Order("XYZ", 10, OrderType.Limit, 50, datetime.now(), account="acct-4")
+    executor.submit_oto(oto_parent, Order("XYZ", -50, OrderType.Limit, 51, datetime.now(), account="acct-4", side=Side.SellShort))
+    with pytest.raises(ValueError) as exc_info:
+        executor.apply_report(oto_parent.order_id, ExecutionReport(ExecType.Fill, 10, 50))
+    assert len(exc_info.value.failed_orders) == 1
+    entry = Order("XYZ", 100, OrderType.Limit, 50, datetime.now(), account="acct-5")
+    exits = [Order("XYZ", -100, OrderType.Limit, price, datetime.now(), account="acct-5") for price in (55, 45)]
+    executor.submit_bracket(entry, *exits)
+    executor.apply_report(entry.order_id, ExecutionReport(ExecType.Fill, 100, 50))
+    assert all(executor.get_order_state(o.order_id).status == OrderStatus.PendingNew for o in exits)
+
+def test_self_trade_prevention():
+    prevention = SelfTradePrevention()
+    prevention.set_policy("cancel", SelfTradePolicy.CancelResting)
+    prevention.set_policy("net", SelfTradePolicy.Net)
+    assert prevention.policy_for("other") == SelfTradePolicy.RejectIncoming
+    validator = OrderValidator()
+    validator.add_symbol("AAPL")
+    for order_type in [OrderType.Market, OrderType.Limit, OrderType.Stop]:
+        validator.set_rule(order_type, ValidationRule(min_quantity=1, max_quantity=1000, min_price=0.01, max_price=10000))
+    executor, sent = make_executor(validator)
+    executor.set_self_trade_prevention(prevention)
+
+    # Strategies on opposite sides of the same account at crossing prices
+    resting = Order("AAPL", 100, OrderType.Limit, 150, datetime.now(), account="reject")
+    executor.execute(resting)
+    with pytest.raises(SelfTradeError) as exc_info:
+        executor.execute(Order("AAPL", -50, OrderType.Limit, 149.5, datetime.now(), account="reject"))
+    assert exc_info.value.resting_order_id == resting.order_id
+    # Prices that don't cross, other accounts and stops are left alone
+    executor.execute(Order("AAPL", -50, OrderType.Limit, 151, datetime.now(), account="reject"))
+    executor.execute(Order("AAPL", -50, OrderType.Limit, 149.5, datetime.now(), account="other"))
+    executor.execute(Order("AAPL", -50, OrderType.Stop, 140, datetime.now(), account="reject"))
+
+    resting = Order("AAPL", 100, OrderType.Limit, 150, datetime.now(), account="cancel")
+    executor.execute(resting)
+    sent.clear()
+    executor.execute(Order("AAPL", -50, OrderType.Market, 150, datetime.now(), account="cancel"))
+    assert [o["request_type"] for o in sent] == ["cancel", "new"]
+    assert executor.get_order_state(resting.order_id).status == OrderStatus.PendingCancel
+    assert len(executor.take_contingent_results()) == 1
+
+    resting = Order("AAPL", 100, OrderType.Limit, 150, datetime.now(), account="net")
+    executor.execute(resting)
+    executor.apply_report(resting.order_id, ExecutionReport(ExecType.Accepted))
+    sent.clear()
+    # Fully netted against the resting order, so nothing new goes out
+    assert executor.execute(Order("AAPL", -40, OrderType.Limit, 150, datetime.now(), account="net")) is None
+    assert [(o["request_type"], o["quantity"]) for o in sent] == [("replace", 60)]
+    executor.apply_report(resting.order_id, ExecutionReport(ExecType.Replaced))
+    sent.clear()
+    executor.execute(Order("AAPL", -150, OrderType.Limit, 150, datetime.now(), account="net"))
+    assert [(o["request_type"], o.get("quantity")) for o in sent] == [("cancel", None), ("new", 90)]
+
+    # Resting orders are left alone when the incoming order fails a later check
+    resting = Order("AAPL", 100, OrderType.Limit, 150, datetime.now(), account="cancel")
+    executor.execute(resting)
+    executor.set_risk_validator(RiskValidator(max_position_size=1000, max_daily_loss=5000, max_order_value=10000))
+    sent.clear()
+    with pytest.raises(RiskLimitError):
+        executor.execute(Order("AAPL", -500, OrderType.Limit, 149, datetime.now(), account="cancel"))
+    assert sent == [] and executor.get_order_state(resting.order_id).status == OrderStatus.PendingNew
+    executor.set_risk_validator(None)
+
+    # Sending an order a second time is refused before any resting order is touched
+    sell = Order("AAPL", -10, OrderType.Limit, 151, datetime.now(), account="cancel")
+    executor.execute(sell)
+    executor.apply_report(sell.order_id, ExecutionReport(ExecType.Fill, 10, 151))
+    buy = Order("AAPL", 10, OrderType.Limit, 152, datetime.now(), account="cancel")
+    executor.execute(buy)
+    sent.clear()
+    with pytest.raises(ValueError):
+        executor.execute(sell)
+    assert sent == [] and executor.get_order_state(buy.order_id).status == OrderStatus.PendingNew
+    executor.apply_report(buy.order_id, ExecutionReport(ExecType.Rejected))
+
+    # Executors for different strategies that share the prevention see each other's orders,
+    # but can't cancel or net them, so crossing one is rejected whatever the policy
+    other, other_sent = make_executor(validator)
+    other.set_self_trade_prevention(prevention)
+    with pytest.raises(SelfTradeError) as exc_info:
+        other.execute(Order("AAPL", -50, OrderType.Limit, 149, datetime.now(), account="cancel", strategy_id="b"))
+    assert exc_info.value.resting_order_id == resting.order_id and other_sent == []
+    assert executor.get_order_state(resting.order_id).status == OrderStatus.PendingNew
+    prevention.set_policy("net-2", SelfTradePolicy.Net)
+    netted = Order("AAPL", 100, OrderType.Limit, 150, datetime.now(), account="net-2")
+    executor.execute(netted)
+    with pytest.raises(SelfTradeError):
+        other.execute(Order("AAPL", -50, OrderType.Limit, 149, datetime.now(), account="net-2", strategy_id="b"))
+    assert executor.get_order_state(netted.order_id).order.quantity == 100 and other_sent == []
+    executor.apply_report(netted.order_id, ExecutionReport(ExecType.Rejected))
+    executor.apply_report(resting.order_id, ExecutionReport(ExecType.Rejected))
+    other.execute(Order("AAPL", -50, OrderType.Limit, 149, datetime.now(), account="cancel", strategy_id="b"))
+
+def test_order_throttling():
+    start = datetime(2024, 3, 1, 15, 0)
+    clock = Clock.simulated(start)
+    throttle = OrderThrottle(duplicate_window=1.0)
+    throttle.set_rate(ThrottleScope.Strategy, 2)
+    throttle.set_rate(ThrottleScope.Symbol, 0.5, burst=3)
+    assert throttle.get_rate(ThrottleScope.Symbol) == (0.5, 3)
+    with pytest.raises(ValueError):
+        throttle.set_rate(ThrottleScope.Account, 0)
+
+    executor, sent = make_executor()
+    executor.set_clock(clock)
+    executor.set_throttle(throttle)
+
+    def order(quantity, strategy="momentum"):
+        return Order("AAPL", quantity, OrderType.Limit, 150, clock.now(), strategy_id=strategy)
+
+    executor.execute(order(1))
+    executor.execute(order(2))
+    with pytest.raises(ThrottledError) as exc_info:
+        executor.execute(order(3))
+    assert exc_info.value.scope == ThrottleScope.Strategy and exc_info.value.key == "momentum"
+    # The symbol bucket is shared with other strategies and refills more slowly
+    executor.execute(order(3, strategy="mean_reversion"))
+    with pytest.raises(ThrottledError) as exc_info:
+        executor.execute(order(4, strategy="mean_reversion"))
+    assert exc_info.value.scope == ThrottleScope.Symbol
+    clock.advance(2)
+    executor.execute(order(4))
+    assert len(sent) == 4
+
+    # An identical order inside the window is refused, whatever the rate
+    throttle.remove_rate(ThrottleScope.Symbol)
+    clock.advance(0.5)
+    with pytest.raises(DuplicateOrderError) as exc_info:
+        executor.execute(order(4))
+    assert exc_info.value.window == 1.0
+    clock.advance(1)
+    executor.execute(order(4))
+    assert issubclass(ThrottledError, OrderValidationError) and issubclass(DuplicateOrderError, ValueError)
+    for bad_rate in (float("nan"), float("inf")):
+        with pytest.raises(ValueError):
+            throttle.set_rate(ThrottleScope.Account, bad_rate)
+
+    # A refused order uses no token and doesn't count as sent
+    restricted = RestrictedList()
+    executor.set_compliance(ComplianceChecker(restricted))
+    restricted.add("AAPL")
+    clock.advance(1)
+    for _ in range(3):
+        with pytest.raises(RestrictedSymbolError):
+            executor.execute(order(5))
+    restricted.remove("AAPL")
+    executor.execute(order(5))
+    executor.set_compliance(None)
+
+    # Amendments the engine makes to prevent a self-trade aren't throttled
+    prevention = SelfTradePrevention()
+    prevention.set_policy("net", SelfTradePolicy.Net)
+    executor.set_self_trade_prevention(prevention)
+    throttle.set_rate(ThrottleScope.Strategy, 1)
+    resting = Order("AAPL", 100, OrderType.Limit, 150, clock.now(), account="net", strategy_id="momentum")
+    executor.execute(resting)
+    executor.apply_report(resting.order_id, ExecutionReport(ExecType.Accepted))
+    clock.advance(1)
+    executor.execute(Order("AAPL", -40, OrderType.Limit, 149, clock.now(), account="net", strategy_id="momentum"))
+    assert executor.get_order_state(resting.order_id).status == OrderStatus.PendingReplace
+
+    # An order the broker callback fails on hands its token back, so a retry goes out
+    calls = []
+    def flaky(order_dict):
+        calls.append(order_dict)
+        if len(calls) == 1:
+            raise RuntimeError("broker unavailable")
+        return order_dict
+    validator = OrderValidator()
+    validator.add_symbol("AAPL")
+    validator.set_rule(OrderType.Limit, ValidationRule(min_quantity=1, max_quantity=1000, min_price=0.01, max_price=10000))
+    flaky_executor = OrderExecutor(validator, flaky)
+    flaky_executor.set_clock(clock)
+    flaky_throttle = OrderThrottle(duplicate_window=1.0)
+    flaky_throttle.set_rate(ThrottleScope.Strategy, 1)
+    flaky_executor.set_throttle(flaky_throttle)
+    with pytest.raises(RuntimeError):
+        flaky_executor.execute(order(6))
+    flaky_executor.execute(order(6))
+    assert len(calls) == 2
+
+def test_position_ledger():
+    executor, sent = make_executor()
+    ledger = executor.get_ledger()
+    ledger.set_position("AAPL", 100, 140, account="acct-1", strategy_id="carry")
+
+    buy = Order("AAPL", 300, OrderType.Limit, 150, datetime.now(), account="acct-1", strategy_id="momentum")
+    executor.execute(buy)
+    assert ledger.pending("AAPL", "acct-1") == (300, 0)
+    executor.apply_report(buy.order_id, ExecutionReport(ExecType.Accepted))
+    executor.apply_report(buy.order_id, ExecutionReport(ExecType.Fill, 100, 150))
+    executor.apply_report(buy.order_id, ExecutionReport(ExecType.Fill, 100, 153))
+    position = ledger.get_position("AAPL", "acct-1", "momentum")
+    assert position.quantity == 200 and position.avg_price == Decimal("151.5")
+    assert ledger.net_position("AAPL", "acct-1") == 300
+    assert ledger.pending("AAPL", "acct-1") == (100, 0)
+    assert ledger.net_position("AAPL") == 0
+
+    # Selling part of a long keeps its cost, flipping short starts afresh
+    sell = Order("AAPL", -250, OrderType.Limit, 155, datetime.now(), account="acct-1", strategy_id="momentum")
+    executor.execute(sell)
+    executor.apply_report(sell.order_id, ExecutionReport(ExecType.Fill, 150, 155))
+    assert ledger.get_position("AAPL", "acct-1", "momentum").avg_price == Decimal("151.5")
+    executor.apply_report(sell.order_id, ExecutionReport(ExecType.Fill, 100, 156))
+    position = ledger.get_position("AAPL", "acct-1", "momentum")
+    assert position.quantity == -50 and position.avg_price == 156
+    executor.apply_report(buy.order_id, ExecutionReport(ExecType.Cancelled))
+    assert ledger.pending("AAPL", "acct-1") == (0, 0)
+    assert len(ledger.positions("acct-1")) == 2
+    with pytest.raises(ValueError):
+        ledger.apply_fill(Order("TSLA", 100, OrderType.Limit, 200, datetime.now()), 0, 200)
+    assert ledger.get_position("TSLA") is None
+
+    # A fill the ledger can't average is refused before any book takes it
+    overflow = Order("AAPL", 100, OrderType.Limit, 150, datetime.now(), account="acct-2")
+    executor.execute(overflow)
+    with pytest.raises(ValueError):
+        executor.apply_report(overflow.order_id, ExecutionReport(ExecType.Fill, 100, Decimal("1e27")))
+    assert ledger.get_position("AAPL", "acct-2") is None
+    assert executor.get_order_state(overflow.order_id).filled_quantity == 0
+    assert executor.get_pnl().pnl("AAPL", "acct-2").realized == 0 and ledger.pending("AAPL", "acct-2") == (100, 0)
+    executor.apply_report(overflow.order_id, ExecutionReport(ExecType.Rejected))
+
+    # The risk validator reads positions and working orders from the ledger
+    risk_validator = RiskValidator(max_position_size=1000, max_daily_loss=5000, max_order_value=1000000)
+    risk_validator.set_ledger(ledger)
+    assert risk_validator.validate_order(Order("AAPL", 900, OrderType.Limit, 150, datetime.now(), account="acct-1"))
+    working = Order("AAPL", 600, OrderType.Limit, 150, datetime.now(), account="acct-1", strategy_id="momentum")
+    executor.execute(working)
+    assert not risk_validator.validate_order(Order("AAPL", 900, OrderType.Limit, 150, datetime.now(), account="acct-1"))
+    assert risk_validator.validate_order(Order("AAPL", -900, OrderType.Limit, 150, datetime.now(), account="acct-1", side=Side.SellShort))
+    # A sale covered by the long is refused once working sales could take it short
+    executor.execute(Order("AAPL", -50, OrderType.Limit, 150, datetime.now(), account="acct-1", strategy_id="carry"))
+    assert not risk_validator.validate_order(Order("AAPL", -10, OrderType.Limit, 150, datetime.now(), account="acct-1"))
+
+    # The executor's risk validator shares its ledger, so an amendment counts
+    # only its own new quantity against the working orders
+    executor.set_risk_validator(RiskValidator(max_position_size=1000, max_daily_loss=5000, max_order_value=1000000))
+    executor.apply_report(working.order_id, ExecutionReport(ExecType.Accepted))
+    executor.replace(working.order_id, 950, 150)
+    with pytest.raises(ValueError):
+        executor.replace(working.order_id, 1000, 150)
+
+def test_pnl_engine():
+    def fills(cost_basis):
+        engine = PnlEngine(cost_basis)
+        engine.apply_fill(Order("AAPL", 100, OrderType.Limit, 100, datetime.now()), 100, 100)
+        engine.apply_fill(Order("AAPL", 100, OrderType.Limit, 110, datetime.now()), 100, 110)
+        realized = engine.apply_fill(Order("AAPL", -150, OrderType.Limit, 120, datetime.now()), 150, 120)
+        engine.update_mark("AAPL", 115)
+        return realized, engine.pnl("AAPL")
+
+    realized, pnl = fills(CostBasis.Fifo)
+    assert realized == 100 * 20 + 50 * 10 and pnl.unrealized == 50 * 5
+    realized, pnl = fills(CostBasis.Lifo)
+    assert realized == 100 * 10 + 50 * 20 and pnl.unrealized == 50 * 15
+    realized, pnl = fills(CostBasis.AverageCost)
+    assert realized == 150 * 15 and pnl.unrealized == 50 * 10
+    assert pnl.total == pnl.realized + pnl.unrealized
+
+    # Futures PnL includes the contract multiplier, and a fill can flip the position
+    registry = InstrumentRegistry()
+    registry.add(Instrument("ES", AssetClass.Future, "CME", "USD", tick_size=Decimal("0.25"), multiplier=50,
+                           expiry=(datetime.now() + timedelta(days=30)).timestamp()))
+    engine = PnlEngine()
+    engine.set_instruments(registry)
+    engine.apply_fill(Order("ES", 2, OrderType.Limit, 5000, datetime.now()), 2, 5000)
+    assert engine.apply_fill(Order("ES", -3, OrderType.Limit, 5010, datetime.now()), 3, 5010) == 2 * 10 * 50
+    engine.update_mark("ES", 5000)
+    assert engine.pnl("ES").unrealized == 10 * 50
+
+    # Empty fills and non-finite timestamps are refused
+    with pytest.raises(ValueError):
+        engine.apply_fill(Order("ES", 1, OrderType.Limit, 5000, datetime.now()), 0, 5000)
+    with pytest.raises(ValueError):
+        engine.apply_fill(Order("ES", 1, OrderType.Limit, 5000, datetime.now()), 1, 5000, float("nan"))
+    assert engine.pnl("ES").unrealized == 10 * 50
+
+    # PnL too large to represent saturates instead of panicking
+    huge = PnlEngine(CostBasis.AverageCost)
+    huge.apply_fill(Order("BIG", 100, OrderType.Limit, 1, datetime.now()), 100, Decimal("1e26"))
+    huge.apply_fill(Order("BIG", 100, OrderType.Limit, 1, datetime.now()), 100, Decimal("2e26"))
+    assert huge.apply_fill(Order("BIG", -50, OrderType.Limit, 1, datetime.now()), 50, Decimal("-5e26")) < 0
+    huge.update_mark("BIG", Decimal("5e26"))
+    assert huge.pnl("BIG").total > 0
+
+    # Daily PnL restarts at the session boundary; totals roll up by strategy and account
+    start = datetime(2024, 3, 1, 15, 0, tzinfo=ZoneInfo("America/New_York"))
+    clock = Clock.simulated(start)
+    executor, sent = make_executor()
+    executor.set_clock(clock)
+    engine = executor.get_pnl()
+    engine.set_session_boundary("17:00", "America/New_York")
+    for strategy, price in [("momentum", 100), ("carry", 101)]:
+        order = Order("AAPL", 100, OrderType.Limit, price, clock.now(), account="acct-1", strategy_id=strategy)
+        executor.execute(order)
+        executor.apply_report(order.order_id, ExecutionReport(ExecType.Fill, 100, price))
+    engine.update_mark("AAPL", 105)
+    assert engine.strategy_pnl("momentum", "acct-1").unrealized == 500
+    assert engine.account_pnl("acct-1").daily == 500 + 400
+    clock.advance(3 * 3600)
+    engine.update_mark("AAPL", 103)
+    assert engine.daily_pnl("acct-1") == -400
+    assert engine.account_pnl("acct-1").unrealized == 300 + 200
+
+    # The executor's risk validator checks max_daily_loss against the engine
+    executor.set_risk_validator(RiskValidator(max_position_size=1000, max_daily_loss=1000, max_order_value=1000000))
+    resting = Order("AAPL", 10, OrderType.Limit, 90, clock.now(), account="acct-1", strategy_id="carry")
+    executor.execute(resting)
+    executor.apply_report(resting.order_id, ExecutionReport(ExecType.Accepted))
+    executor.replace(resting.order_id, 20, 90)
+    engine.update_mark("AAPL", 95)
+    with pytest.raises(ValueError):
+        executor.replace(resting.order_id, 30, 90)
+
+    # A fill the PnL engine refuses leaves every book as it was
+    with pytest.raises(ValueError):
+        executor.apply_report(resting.order_id, ExecutionReport(ExecType.Fill, 10, 90, timestamp=float("nan")))
+    assert executor.get_order_state(resting.order_id).filled_quantity == 0
+    assert executor.get_ledger().get_position("AAPL", "acct-1", "carry").quantity == 100
+
+def test_risk_decision():
+    risk_validator = RiskValidator(max_position_size=1000, max_daily_loss=5000, max_order_value=100000)
+    decision = risk_validator.check_order(Order("AAPL", 100, OrderType.Limit, 150, datetime.now()), current_position=500, daily_pnl=-1000)
+    assert decision and decision.approved and decision.breaches() == []
+    position = [c for c in decision.checks if c.limit == RiskLimit.Position][0]
+    assert (position.limit_value, position.observed, position.headroom) == (1000, 600, 400)
+
+    order = Order("AAPL", 800, OrderType.Limit, 150, datetime.now())
+    decision = risk_validator.check_order(order, current_position=500, daily_pnl=-6000)
+    assert not decision and decision.order_id == order.order_id
+    value, loss, position = decision.breaches()
+    assert [value.limit, loss.limit, position.limit] == [RiskLimit.OrderValue, RiskLimit.DailyLoss, RiskLimit.Position]
+    assert (loss.observed, loss.headroom) == (6000, -1000)
+    assert value.headroom == -20000 and position.breached
+    assert not risk_validator.validate_order(order, 500, -6000)
+
+    decision = risk_validator.check_order(Order("AAPL", -150, OrderType.Limit, 150, datetime.now()), current_position=100)
+    assert [(c.limit, c.headroom) for c in decision.breaches()] == [(RiskLimit.SellBeyondLong, -50)]
+
+    # Executor rejections carry the decision
+    executor, sent = make_executor()
+    executor.set_risk_validator(risk_validator)
+    order = Order("AAPL", 100, OrderType.Limit, 150, datetime.now())
+    executor.execute(order)
+    executor.apply_report(order.order_id, ExecutionReport(ExecType.Accepted))
+    with pytest.raises(RiskLimitError) as exc_info:
+        executor.replace(order.order_id, 100, 150, daily_pnl=-5001)
+    assert [c.limit for c in exc_info.value.decision.breaches()] == [RiskLimit.DailyLoss]
+    assert issubclass(RiskLimitError, ValueError)
+
+    # New orders are checked before they reach the broker
+    sent.clear()
+    with pytest.raises(RiskLimitError) as exc_info:
+        executor.execute(Order("AAPL", 950, OrderType.Limit, 50, datetime.now()))
+    assert [c.limit for c in exc_info.value.decision.breaches()] == [RiskLimit.Position]
+    assert sent == [] and len(executor.open_orders()) == 1
+
+def test_hierarchical_limits():
+    registry = InstrumentRegistry()
+    registry.add(Instrument("AAPL", AssetClass.Equity, "NASDAQ", "USD", sector="Technology"))
+    registry.add(Instrument("MSFT", AssetClass.Equity, "NASDAQ", "USD", sector="Technology"))
+    registry.add(Instrument("XOM", AssetClass.Equity, "NYSE", "USD", sector="Energy"))
+    ledger = PositionLedger()
+    pnl = PnlEngine()
+    risk_validator = RiskValidator(max_position_size=100000, max_daily_loss=100000, max_order_value=100000000)
+    risk_validator.set_instruments(registry)
+    risk_validator.set_ledger(ledger)
+    risk_validator.set_pnl(pnl)
+    limits = risk_validator.get_limits()
+
+    limits.set(LimitScope.Firm, ScopeLimits(max_order_value=1000000))
+    limits.set(LimitScope.Account, ScopeLimits(max_order_quantity=5000), key="acct-1")
+    limits.set(LimitScope.Strategy, ScopeLimits(max_position=1000), key="momentum")
+    limits.set(LimitScope.Instrument, ScopeLimits(max_position=1500), key="AAPL")
+    limits.set(LimitScope.Sector, ScopeLimits(max_daily_loss=2000), key="Technology")
+    limits.set(LimitScope.AssetClass, ScopeLimits(max_order_quantity=4000), key="Equity")
+    assert len(limits) == 6 and (LimitScope.Firm, None) in limits.scopes()
+    with pytest.raises(ValueError):
+        limits.set(LimitScope.Account, ScopeLimits(max_position=1))
+
+    def breaches(order):
+        return [(c.scope, c.scope_key, c.limit) for c in risk_validator.check_order(order).breaches()]
+
+    assert breaches(Order("AAPL", 4500, OrderType.Limit, 150, datetime.now(), account="acct-1")) == [
+        (LimitScope.Instrument, "AAPL", RiskLimit.Position), (LimitScope.AssetClass, "Equity", RiskLimit.OrderQuantity)]
+    decision = risk_validator.check_order(Order("XOM", 4500, OrderType.Limit, 100, datetime.now(), account="acct-1"))
+    assert [(c.scope, c.limit) for c in decision.breaches()] == [(LimitScope.AssetClass, RiskLimit.OrderQuantity)]
+    assert breaches(Order("XOM", 3000, OrderType.Limit, 400, datetime.now(), account="acct-2")) == [
+        (LimitScope.Firm, None, RiskLimit.OrderValue)]
+
+    # Position limits count every holding in the scope, whichever account holds it
+    ledger.set_position("AAPL", 900, 150, account="acct-2", strategy_id="momentum")
+    ledger.set_position("AAPL", 400, 150, account="acct-1", strategy_id="carry")
+    assert breaches(Order("AAPL", 300, OrderType.Limit, 150, datetime.now(), account="acct-1", strategy_id="momentum")) == [
+        (LimitScope.Strategy, "momentum", RiskLimit.Position), (LimitScope.Instrument, "AAPL", RiskLimit.Position)]
+    assert breaches(Order("AAPL", 100, OrderType.Limit, 150, datetime.now(), account="acct-1", strategy_id="momentum")) == []
+    assert breaches(Order("MSFT", 1200, OrderType.Limit, 300, datetime.now(), account="acct-1", strategy_id="momentum")) == [
+        (LimitScope.Strategy, "momentum", RiskLimit.Position)]
+
+    # Sector losses add up across instruments
+    pnl.apply_fill(Order("MSFT", 100, OrderType.Limit, 300, datetime.now(), account="acct-1"), 100, 300)
+    pnl.apply_fill(Order("AAPL", 100, OrderType.Limit, 150, datetime.now(), account="acct-2"), 100, 150)
+    pnl.update_mark("MSFT", 288)
+    pnl.update_mark("AAPL", 140)
+    assert breaches(Order("MSFT", 10, OrderType.Limit, 288, datetime.now(), account="acct-3")) == [
+        (LimitScope.Sector, "Technology", RiskLimit.DailyLoss)]
+    assert breaches(Order("XOM", 10, OrderType.Limit, 100, datetime.now(), account="acct-3")) == []
+
+    # Usage reports what is left of each scope's limits without an order
+    loss, = risk_validator.usage(LimitScope.Sector, "Technology")
+    assert (loss.observed, loss.headroom) == (2200, -200)
+    position, = risk_validator.usage(LimitScope.Instrument, "AAPL")
+    assert (position.observed, position.headroom) == (1300, 200)
+    position, = risk_validator.usage(LimitScope.Strategy, "momentum", symbol="AAPL")
+    assert position.headroom == 100
+    assert risk_validator.usage(LimitScope.Account, "acct-9") == []
+
+    # Limits change at runtime, and the book can be shared between validators
+    assert limits.get(LimitScope.Sector, "Technology").max_daily_loss == 2000
+    limits.set(LimitScope.Sector, ScopeLimits(max_daily_loss=5000), key="Technology")
+    assert breaches(Order("MSFT", 10, OrderType.Limit, 288, datetime.now(), account="acct-3")) == []
+    assert limits.remove(LimitScope.Strategy, "momentum").max_position == 1000
+    other = RiskValidator(max_position_size=100000, max_daily_loss=100000, max_order_value=100000000)
+    other.set_limits(limits)
+    assert other.check_order(Order("XOM", 4500, OrderType.Limit, 10, datetime.now(), account="acct-1")).approved
+    other.set_instruments(registry)
+    assert [c.scope for c in other.check_order(Order("XOM", 4500, OrderType.Limit, 10, datetime.now(), account="acct-1")).breaches()] == [
+        LimitScope.AssetClass]
+
+def test_exposure_limits():
+    registry = InstrumentRegistry()
+    registry.add(Instrument("AAPL", AssetClass.Equity, "NASDAQ", "USD", sector="Technology"))
+    registry.add(Instrument("MSFT", AssetClass.Equity, "NASDAQ", "USD", sector="Technology"))
+    registry.add(Instrument("XOM", AssetClass.Equity, "NYSE", "USD", sector="Energy"))
+    registry.add(Instrument("ES", AssetClass.Future, "CME", "USD", tick_size=Decimal("0.25"), multiplier=50,
+                            expiry=(datetime.now() + timedelta(days=30)).timestamp()))
+    ledger = PositionLedger()
+    pnl = PnlEngine()
+    risk_validator = RiskValidator(max_position_size=100000, max_daily_loss=100000, max_order_value=100000000)
+    risk_validator.set_instruments(registry)
+    risk_validator.set_ledger(ledger)
+    risk_validator.set_pnl(pnl)
+    ledger.set_position("AAPL", 1000, 140, account="acct-1")
+    ledger.set_position("XOM", -2000, 100, account="acct-1")
+    ledger.set_position("MSFT", 500, 300, account="acct-2")
+    pnl.update_mark("AAPL", 150)
+
+    with pytest.raises(ValueError):
+        ScopeLimits(max_sector_concentration=30)
+    risk_validator.get_limits().set(LimitScope.Account, ScopeLimits(
+        max_gross_exposure=400000, max_net_exposure=100000, max_long_exposure=250000, max_short_exposure=250000,
+        max_sector_concentration=30, max_open_positions=2, equity=1000000), key="acct-1")
+
+    # acct-1 holds 150,000 long in Technology (at the AAPL mark) and 200,000 short in Energy
+    def check(order):
+        checks = [c for c in risk_validator.check_order(order).checks if c.scope == LimitScope.Account]
+        return lambda limit: next((c for c in checks if c.limit == limit), None)
+
+    limit = check(Order("MSFT", 400, OrderType.Limit, 300, datetime.now(), account="acct-1"))
+    assert limit(RiskLimit.GrossExposure).observed == 150000 + 200000 + 120000
+    assert limit(RiskLimit.LongExposure).observed == 270000 and limit(RiskLimit.LongExposure).breached
+    assert limit(RiskLimit.NetExposure).observed == 70000
+    assert limit(RiskLimit.SectorConcentration).observed == 27
+    assert limit(RiskLimit.OpenPositions).observed == 3 and limit(RiskLimit.OpenPositions).breached
+
+    # Futures are valued with their multiplier; concentration only applies to sectors
+    limit = check(Order("ES", 1, OrderType.Limit, 5000, datetime.now(), account="acct-1"))
+    assert limit(RiskLimit.LongExposure).observed == 150000 + 250000
+    assert limit(RiskLimit.SectorConcentration) is None
+
+    # Covering the short shrinks gross exposure but leaves the book net long
+    limit = check(Order("XOM", 1500, OrderType.Limit, 100, datetime.now(), account="acct-1", side=Side.BuyToCover))
+    assert limit(RiskLimit.GrossExposure).observed == 200000 and limit(RiskLimit.ShortExposure).observed == 50000
+    assert limit(RiskLimit.NetExposure).observed == 100000 and not limit(RiskLimit.NetExposure).breached
+    limit = check(Order("AAPL", 1000, OrderType.Limit, 150, datetime.now(), account="acct-1"))
+    assert limit(RiskLimit.SectorConcentration).observed == 30 and limit(RiskLimit.LongExposure).breached
+    assert not risk_validator.validate_order(Order("AAPL", 1100, OrderType.Limit, 150, datetime.now(), account="acct-1"))
+
+    # A firm-wide limit covers every account's holdings
+    risk_validator.get_limits().set(LimitScope.Firm, ScopeLimits(max_gross_exposure=500000))
+    decision = risk_validator.check_order(Order("MSFT", 100, OrderType.Limit, 300, datetime.now(), account="acct-3"))
+    assert [(c.scope, c.limit, c.observed) for c in decision.breaches()] == [
+        (LimitScope.Firm, RiskLimit.GrossExposure, 150000 + 200000 + 180000)]
+
+    # A market order with nothing to price it at can't be valued, so it is refused
+    risk_validator.get_limits().set(LimitScope.Account, ScopeLimits(max_gross_exposure=1000), key="acct-4")
+    decision = risk_validator.check_order(Order("GOOG", 1000, OrderType.Market, 0, datetime.now(), account="acct-4"))
+    assert [(c.scope, c.limit) for c in decision.breaches()] == [
+        (LimitScope.Firm, RiskLimit.GrossExposure), (LimitScope.Account, RiskLimit.GrossExposure)]
+    risk_validator.get_limits().remove(LimitScope.Firm)
+    pnl.update_mark("GOOG", 1)
+    assert risk_validator.check_order(Order("GOOG", 1000, OrderType.Market, 0, datetime.now(), account="acct-4")).approved
+
+    # Equity can only be set to a positive value
+    limits = ScopeLimits(max_sector_concentration=30, equity=1000000)
+    for bad_equity in (-5, 0, None):
+        with pytest.raises(ValueError):
+            limits.equity = bad_equity
+    limits.equity = 2000000
+    limits.max_sector_concentration = None
+    limits.equity = None
+
+    usage = risk_validator.usage(LimitScope.Account, "acct-1")
+    assert [(c.limit, c.observed) for c in usage] == [
+        (RiskLimit.GrossExposure, 350000), (RiskLimit.NetExposure, 50000), (RiskLimit.LongExposure, 150000),
+        (RiskLimit.ShortExposure, 200000), (RiskLimit.SectorConcentration, 20), (RiskLimit.OpenPositions, 2)]
+
 def test_strategy_manager():
     strategy_manager = StrategyManager()
 